@@ -0,0 +1,286 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use log::debug;
+use pulldown_cmark::Options;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::DataPages, highlight::HighlightConfig, i18n::LanguageConfig, menu::MenuEntry,
+    permalink::join_page_path, redirect::RedirectConfig, slugify::SlugifyConfig, toc::AnchorLinks,
+    vfs::SourceFs,
+};
+
+/// Site wide configuration, read from `config.toml` at the root of the template directory.
+/// Every field is optional, so a site without `config.toml` gets all the default values.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct SiteConfig {
+    /// The URL the site is hosted on e.g. `https://example.com/`, used by the `absolute_url` and
+    /// `relative_url` template helpers
+    pub base_url: String,
+    /// Render contents to `<slug>.html` instead of `<slug>/index.html`, for hosting without
+    /// directory index
+    pub ugly_urls: bool,
+    /// Make every root relative URL (starting with `/`) on the rendered pages relative to the
+    /// page itself, so the site works when opened straight from disk
+    pub relative_urls: bool,
+    pub markdown: MarkdownConfig,
+    pub slugify: SlugifyConfig,
+    /// Permalink pattern of the contents per directory, keyed by the directory path e.g.
+    /// `blog = "/blog/:year/:month/:slug/"`, see [`crate::permalink::expand_permalink`]
+    pub permalinks: HashMap<String, String>,
+    pub redirects: RedirectConfig,
+    /// Named menus, e.g. `[[menus.main]]`, more entries can be added from the front matter of the
+    /// contents, see [`crate::menu::Menus`]
+    pub menus: HashMap<String, Vec<MenuEntry>>,
+    /// Pages generated from data files, see [`crate::data::DataPages`]
+    pub data_pages: Vec<DataPages>,
+    /// Language of the contents without a language suffix, defaults to `en`
+    pub default_language: Option<String>,
+    /// Languages of the site keyed by their code e.g. `[languages.id]`, a content file with one
+    /// of the codes as suffix e.g. `post.id.md` is the translation of `post.md`
+    pub languages: BTreeMap<String, LanguageConfig>,
+    /// Write `sitemap.xml` for each language, see [`crate::sitemap`], needs `base_url`
+    pub sitemap: bool,
+}
+
+impl SiteConfig {
+    pub const FILE_NAME: &'static str = "config.toml";
+
+    /// Permalink pattern of the contents on the directory `section`, if there's any.
+    pub fn get_permalink(&self, section: &str) -> Option<&String> {
+        self.permalinks
+            .iter()
+            .find(|(key, _)| join_page_path([key.as_str()]) == section)
+            .map(|(_, pattern)| pattern)
+    }
+
+    pub fn default_language(&self) -> &str {
+        self.default_language.as_deref().unwrap_or("en")
+    }
+
+    /// Every language other than the default language.
+    pub fn other_languages(&self) -> impl Iterator<Item = &str> {
+        self.languages
+            .keys()
+            .map(String::as_str)
+            .filter(|lang| *lang != self.default_language())
+    }
+
+    /// The page path every page of `lang` is put under, the default language is always on the
+    /// root, others default to their code e.g. `id`.
+    pub fn language_prefix(&self, lang: &str) -> String {
+        if lang == self.default_language() {
+            return String::new();
+        }
+
+        let prefix = self
+            .languages
+            .get(lang)
+            .and_then(|language| language.prefix.as_deref())
+            .unwrap_or(lang);
+
+        join_page_path([prefix])
+    }
+
+    /// Display name of `lang`, defaults to its code.
+    pub fn language_name(&self, lang: &str) -> String {
+        self.languages
+            .get(lang)
+            .and_then(|language| language.name.clone())
+            .unwrap_or_else(|| lang.to_owned())
+    }
+
+    /// The language suffix of a content file e.g. `id` for `post.id.md`, only the codes on
+    /// `languages` are taken as suffix.
+    pub fn content_language(&self, source_path: &Path) -> Option<&str> {
+        let file_stem = source_path.file_stem()?.to_str()?;
+        let (_, suffix) = file_stem.rsplit_once('.')?;
+
+        self.languages
+            .get_key_value(suffix)
+            .map(|(lang, _)| lang.as_str())
+    }
+
+    /// This function assume that `root_path` is the root of all of the pages, a missing
+    /// `config.toml` is not an error, but a malformed one is.
+    pub fn from_dir<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_path = root_path.as_ref().join(Self::FILE_NAME);
+
+        if !source_fs.is_file(&config_path) {
+            debug!(
+                stage = "load";
+                "[SiteConfig::from_dir] No {} found, using default config",
+                Self::FILE_NAME
+            );
+
+            return Ok(Self::default());
+        }
+
+        let raw_config = source_fs.read_to_string(&config_path)?;
+        let config: Self = toml::from_str(&raw_config)?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Check the values that cannot be checked while parsing, also used for a config passed to
+    /// [`crate::site::SiteBuilder::config`].
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let highlight = &self.markdown.highlight;
+        if highlight.enabled && highlight.get_theme().is_none() {
+            return Err(format!("Unknown highlight theme '{}'!", highlight.theme).into());
+        }
+
+        if !self.languages.is_empty() && !self.languages.contains_key(self.default_language()) {
+            return Err(format!(
+                "Default language '{}' is not on languages!",
+                self.default_language()
+            )
+            .into());
+        }
+
+        // Sitemap URLs need to be absolute
+        if self.sitemap && self.base_url.is_empty() {
+            return Err("Sitemap needs base_url!".into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Toggles for the pulldown-cmark extensions, the defaults follow what GitHub-flavored markdown
+/// would support.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct MarkdownConfig {
+    pub tables: bool,
+    pub footnotes: bool,
+    pub strikethrough: bool,
+    pub tasklists: bool,
+    pub heading_attributes: bool,
+    pub smart_punctuation: bool,
+    /// Add a `#` link to every heading, `"before"` or `"after"` the heading text
+    pub anchor_links: AnchorLinks,
+    pub highlight: HighlightConfig,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            heading_attributes: false,
+            smart_punctuation: false,
+            anchor_links: AnchorLinks::default(),
+            highlight: HighlightConfig::default(),
+        }
+    }
+}
+
+impl MarkdownConfig {
+    /// Returns a copy of the config with every field set on `overrides` replacing the site value.
+    pub fn merge(&self, overrides: &MarkdownOverrides) -> Self {
+        Self {
+            tables: overrides.tables.unwrap_or(self.tables),
+            footnotes: overrides.footnotes.unwrap_or(self.footnotes),
+            strikethrough: overrides.strikethrough.unwrap_or(self.strikethrough),
+            tasklists: overrides.tasklists.unwrap_or(self.tasklists),
+            heading_attributes: overrides
+                .heading_attributes
+                .unwrap_or(self.heading_attributes),
+            smart_punctuation: overrides
+                .smart_punctuation
+                .unwrap_or(self.smart_punctuation),
+            anchor_links: self.anchor_links,
+            highlight: self.highlight.clone(),
+        }
+    }
+
+    pub fn to_options(&self) -> Options {
+        let mut options = Options::empty();
+
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_HEADING_ATTRIBUTES, self.heading_attributes);
+        options.set(Options::ENABLE_SMART_PUNCTUATION, self.smart_punctuation);
+
+        options
+    }
+}
+
+/// Per page markdown toggles, set from the front matter as `markdown = { tables = false }`.
+/// Unset fields fall back to the site config.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct MarkdownOverrides {
+    pub tables: Option<bool>,
+    pub footnotes: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub tasklists: Option<bool>,
+    pub heading_attributes: Option<bool>,
+    pub smart_punctuation: Option<bool>,
+}
+
+#[cfg(test)]
+mod config_test {
+    use pulldown_cmark::Options;
+
+    use crate::{
+        config::{MarkdownConfig, MarkdownOverrides, SiteConfig},
+        vfs::MemoryFs,
+    };
+
+    #[test]
+    fn partial_config_uses_defaults() {
+        let config: SiteConfig = toml::from_str("[markdown]\nsmart_punctuation = true").unwrap();
+
+        assert!(
+            config.markdown.smart_punctuation,
+            "Set field should be parsed!"
+        );
+        assert!(
+            config.markdown.tables,
+            "Unset field should fall back to the default!"
+        );
+    }
+
+    #[test]
+    fn overrides_replace_only_set_fields() {
+        let overrides = MarkdownOverrides {
+            tables: Some(false),
+            ..Default::default()
+        };
+
+        let options = MarkdownConfig::default().merge(&overrides).to_options();
+
+        assert!(
+            !options.contains(Options::ENABLE_TABLES),
+            "Overridden field should be disabled!"
+        );
+        assert!(
+            options.contains(Options::ENABLE_FOOTNOTES),
+            "Non overridden field should keep the site value!"
+        );
+    }
+
+    #[test]
+    fn sitemap_needs_base_url() {
+        let source_fs = MemoryFs::new().with_file(&SiteConfig::FILE_NAME, "sitemap = true");
+
+        assert!(
+            SiteConfig::from_dir(&source_fs, &"").is_err(),
+            "Sitemap without base_url should be an error!"
+        );
+    }
+}
@@ -1,39 +1,51 @@
-use std::{
-    fs::{self, File},
-    io::{Read, Result},
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use log::{debug, trace, warn};
 use pulldown_cmark::Parser;
 use serde::Serialize;
-
-use crate::preparser::ContentMetadata;
+use serde_json::Value;
+
+use crate::{
+    highlight::highlight_code_blocks,
+    preparser::ContentMetadata,
+    renderer::Renderer,
+    report::SkippedFile,
+    toc::{add_heading_ids, TocEntry},
+    vfs::SourceFs,
+};
 
 #[derive(Debug, Serialize, Clone)]
 pub struct Content {
     #[serde(skip_serializing)]
     raw_content: String,
+    /// The file this content is parsed from, used to resolve relative links
+    #[serde(skip_serializing)]
+    pub(crate) source_path: PathBuf,
     pub metadata: ContentMetadata,
 }
 
 impl Content {
-    fn get_clean_list_of_content_paths<P: AsRef<Path>>(content_root_dir: &P) -> Vec<PathBuf> {
+    fn get_clean_list_of_content_paths<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        content_root_dir: &P,
+    ) -> Vec<PathBuf> {
         let mut list_of_contents = Vec::<PathBuf>::new();
 
-        if let Ok(entries) = fs::read_dir(content_root_dir) {
-            for entry in entries.flatten() {
-                let entry_path = entry.path();
-                if entry.file_type().is_ok_and(|file_type| {
-                    file_type.is_file()
-                        && entry_path
-                            .extension()
-                            .is_some_and(|extension| extension == "md")
-                        && entry_path
-                            .file_name()
-                            // Index content should always be processed separately
-                            .is_some_and(|file_name| file_name != "_index.md")
-                }) {
-                    list_of_contents.push(entry_path);
+        if let Ok(entries) = source_fs.read_dir(content_root_dir.as_ref()) {
+            for entry in entries {
+                if !entry.is_dir
+                    && entry
+                        .path
+                        .extension()
+                        .is_some_and(|extension| extension == "md")
+                    && entry
+                        .path
+                        .file_name()
+                        // Index content should always be processed separately
+                        .is_some_and(|file_name| file_name != "_index.md")
+                {
+                    list_of_contents.push(entry.path);
                 }
             }
         }
@@ -41,54 +53,169 @@ impl Content {
         list_of_contents
     }
 
-    pub fn from_file<P: AsRef<Path>>(file: &P) -> Result<Self> {
-        let mut opened_file = File::open(file)?;
-        let mut raw_content = String::new();
-        opened_file.read_to_string(&mut raw_content)?;
+    pub fn from_file<P: AsRef<Path>>(source_fs: &dyn SourceFs, file: &P) -> std::io::Result<Self> {
+        let raw_content = source_fs.read_to_string(file.as_ref())?;
 
         // TODO: Make this more succint and better to read, because right now, wtf?
-        let current_path: String = match Path::new(file.as_ref()).file_stem() {
-            Some(osstr_file_name) => {
-                osstr_file_name
-                    .to_os_string()
-                    .into_string()
-                    .or(Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        "Cannot make OsString to string!",
-                    )))?
-            }
-            None => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Cannot stem file name",
-                ))
+        match Path::new(file.as_ref()).file_stem() {
+            Some(osstr_file_name) if osstr_file_name.to_str().is_none() => {
+                return Err(std::io::Error::other("Cannot make OsString to string!"))
             }
+            None => return Err(std::io::Error::other("Cannot stem file name")),
+            _ => {}
         };
 
+        Ok(Self::from_markdown(
+            raw_content,
+            file.as_ref().to_path_buf(),
+        ))
+    }
+
+    /// Parse a content from memory, `source_path` is where the content would be on the template
+    /// directory (e.g. `blog/hello.md`), its file stem is the default slug and title.
+    pub fn from_markdown(markdown: String, source_path: PathBuf) -> Self {
+        let file_name = source_path
+            .file_stem()
+            .map(|file_stem| file_stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
         // The result should metadata and remove the meatadata part on the raw_content
         let (metadata, processed_content) =
-            ContentMetadata::preprocess_content_metadata(raw_content, current_path);
+            ContentMetadata::preprocess_content_metadata(markdown, file_name);
 
-        Ok(Self {
+        Self {
             raw_content: processed_content,
+            source_path,
+            metadata,
+        }
+    }
+
+    /// The markdown of the content, without the front matter.
+    pub fn raw_content(&self) -> &str {
+        &self.raw_content
+    }
+
+    pub fn set_raw_content(&mut self, raw_content: String) {
+        self.raw_content = raw_content;
+    }
+
+    pub fn source_path(&self) -> &Path {
+        &self.source_path
+    }
+
+    /// Set the language of the content, the `.<lang>` suffix of the file name e.g. `post.id.md`
+    /// is removed from the slug and title taken from it.
+    pub fn set_language(&mut self, lang: &str) {
+        let suffix = format!(".{lang}");
+        let file_stem = self
+            .source_path
+            .file_stem()
+            .map(|file_stem| file_stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if let Some(name) = file_stem.strip_suffix(&suffix) {
+            if self.metadata.is_slug_generated {
+                self.metadata.slug = Some(name.to_owned());
+            }
+            if self.metadata.title.as_deref() == Some(file_stem.as_str()) {
+                self.metadata.title = Some(name.to_owned());
+            }
+        }
+
+        self.metadata.lang = Some(lang.to_owned());
+    }
+
+    /// Make a content from one record of a data file, the record fields are used as the front
+    /// matter, and its `content` field as the markdown. Without `slug` and `title` both are set
+    /// to `fallback_name`, and the whole record is kept as `record` for the templates.
+    pub fn from_data_record(
+        record: &Value,
+        source_path: PathBuf,
+        fallback_name: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let Value::Object(fields) = record else {
+            return Err(format!("Data record {source_path:?} is not a table!").into());
+        };
+
+        let mut metadata_fields = fields.clone();
+        let raw_content = match metadata_fields.remove("content") {
+            Some(Value::String(raw_content)) => raw_content,
+            Some(_) => return Err(format!("Content of {source_path:?} is not a string!").into()),
+            None => String::new(),
+        };
+        // Dates on data files are plain string, so it's parsed here instead of by serde
+        let updated_at = metadata_fields.remove("updated_at");
+
+        let mut metadata: ContentMetadata = serde_json::from_value(Value::Object(metadata_fields))
+            .map_err(|err| format!("Cannot parse data record {source_path:?}! {err}"))?;
+
+        if let Some(updated_at) = updated_at {
+            let updated_at = updated_at.as_str().unwrap_or_default();
+            metadata.updated_at = DateTime::parse_from_rfc3339(updated_at)
+                .map(|date| date.with_timezone(&Utc))
+                .or_else(|_| {
+                    NaiveDate::parse_from_str(updated_at, "%Y-%m-%d")
+                        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+                })
+                .map_err(|_| format!("Cannot parse updated_at of {source_path:?}!"))?;
+            metadata.is_date_set = true;
+        }
+
+        if metadata.slug.is_none() {
+            metadata.slug = Some(
+                metadata
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| fallback_name.to_owned()),
+            );
+            metadata.is_slug_generated = true;
+        }
+        if metadata.title.is_none() {
+            metadata.title = Some(fallback_name.to_owned());
+        }
+        metadata.record = Some(record.clone());
+
+        Ok(Self {
+            raw_content,
+            source_path,
             metadata,
         })
     }
 
     /// This function assume that `path` is the root of `contents` path
-    pub fn from_dir<P: AsRef<Path>>(dir: &P) -> Vec<Self> {
-        let all_content_paths = Self::get_clean_list_of_content_paths(dir);
+    pub fn from_dir<P: AsRef<Path>>(source_fs: &dyn SourceFs, dir: &P) -> Vec<Self> {
+        Self::from_dir_or_skip(source_fs, dir, &mut Vec::new())
+    }
+
+    /// Same as [`Content::from_dir`], but also collects the files that cannot be parsed.
+    pub(crate) fn from_dir_or_skip<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        dir: &P,
+        skipped_files: &mut Vec<SkippedFile>,
+    ) -> Vec<Self> {
+        let all_content_paths = Self::get_clean_list_of_content_paths(source_fs, dir);
         let mut contents = Vec::<Self>::with_capacity(all_content_paths.len());
 
-        println!("[Content::from_dir] Starting to parse: {contents:#?}");
+        debug!(
+            stage = "load", file:% = dir.as_ref().display();
+            "[Content::from_dir] Parsing {} contents of {:?}",
+            all_content_paths.len(),
+            dir.as_ref()
+        );
 
         for content_path in all_content_paths {
-            println!("[Content::from_dir] Parsing file '{content_path:#?}");
+            trace!(stage = "load", file:% = content_path.display(); "[Content::from_dir] Parsing file {content_path:?}");
 
-            match Self::from_file(&content_path) {
+            match Self::from_file(source_fs, &content_path) {
                 Ok(content) => contents.push(content),
                 // This should be unlikely but, who knows?
-                Err(err) => println!("[Content::from_dir] Error parsing content on path '{content_path:#?}'! Error message: '{err:#?}'")
+                Err(err) => {
+                    warn!(stage = "load", file:% = content_path.display(); "[Content::from_dir] Error parsing content on path {content_path:?}! Error: {err}");
+                    skipped_files.push(SkippedFile::new(
+                        &content_path,
+                        format!("cannot parse, {err}"),
+                    ));
+                }
             };
         }
 
@@ -98,13 +225,43 @@ impl Content {
         contents
     }
 
-    pub fn to_html(&self) -> String {
-        let parser = Parser::new(&self.raw_content);
-        let mut html_output = String::new();
+    /// Render the markdown content with the site markdown config, overridden by the
+    /// `markdown` table of the content front matter if there's any.
+    pub fn to_html(&self, renderer: &Renderer) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.to_html_with_toc(renderer)?.0)
+    }
 
-        pulldown_cmark::html::push_html(&mut html_output, parser);
+    /// Same as [`Content::to_html`], but also returns the table of contents of the headings.
+    pub fn to_html_with_toc(
+        &self,
+        renderer: &Renderer,
+    ) -> Result<(String, Vec<TocEntry>), Box<dyn std::error::Error>> {
+        let markdown_config = &renderer.config.markdown;
+        let options = match &self.metadata.markdown {
+            Some(overrides) => markdown_config.merge(overrides).to_options(),
+            None => markdown_config.to_options(),
+        };
+
+        let markdown = renderer.shortcodes.process(&self.raw_content)?;
+        let mut events = renderer
+            .internal_links
+            .rewrite_links(Parser::new_ext(&markdown, options), &self.source_path)?;
+        events = renderer.plugins.markdown_events(events)?;
+
+        if markdown_config.highlight.enabled {
+            events = highlight_code_blocks(events.into_iter(), &markdown_config.highlight);
+        }
+
+        let (events, toc) = add_heading_ids(
+            events.into_iter(),
+            markdown_config.anchor_links,
+            &renderer.config.slugify,
+        );
+
+        let mut html_output = String::new();
+        pulldown_cmark::html::push_html(&mut html_output, events.into_iter());
 
-        html_output
+        Ok((html_output, toc))
     }
 }
 
@@ -112,7 +269,7 @@ impl Content {
 mod content_test {
     use std::path::PathBuf;
 
-    use crate::content::Content;
+    use crate::{content::Content, preparser::ContentMetadata, renderer::Renderer, vfs::DiskFs};
 
     const MAIN_DIR: &str = env!("CARGO_MANIFEST_DIR");
 
@@ -130,7 +287,7 @@ mod content_test {
 
         println!("[content_test::test_content_file_parsing] reading from file path: {test_dir:#?}");
 
-        let content = Content::from_file(&test_dir);
+        let content = Content::from_file(&DiskFs, &test_dir);
 
         assert!(
             content.is_ok(),
@@ -158,7 +315,7 @@ mod content_test {
             "Known valid content SHOULD parse metadata succesfully!"
         );
 
-        let html_ouput = content.unwrap().to_html();
+        let html_ouput = content.unwrap().to_html(&Renderer::default()).unwrap();
 
         assert!(
             !html_ouput.is_empty(),
@@ -170,7 +327,7 @@ mod content_test {
     fn test_directory_parsing() {
         let test_dir = get_path_to_test_files();
 
-        let contents = Content::from_dir(&test_dir);
+        let contents = Content::from_dir(&DiskFs, &test_dir);
 
         assert_eq!(
             contents.len(),
@@ -185,4 +342,43 @@ mod content_test {
             "Content from directory did not match the known content!"
         );
     }
+
+    #[test]
+    fn test_markdown_extensions() {
+        let test_data =
+            "---\nmarkdown = { tables = false }\n---\n\n| a | b |\n|---|---|\n| 1 | 2 |";
+        let table_data = "| a | b |\n|---|---|\n| 1 | 2 |";
+
+        let (metadata, raw_content) =
+            ContentMetadata::preprocess_content_metadata(table_data.into(), "table".into());
+        let content = Content {
+            raw_content,
+            source_path: "table.md".into(),
+            metadata,
+        };
+
+        assert!(
+            content
+                .to_html(&Renderer::default())
+                .unwrap()
+                .contains("<table>"),
+            "Tables should be enabled by default!"
+        );
+
+        let (metadata, raw_content) =
+            ContentMetadata::preprocess_content_metadata(test_data.into(), "no-table".into());
+        let content = Content {
+            raw_content,
+            source_path: "table.md".into(),
+            metadata,
+        };
+
+        assert!(
+            !content
+                .to_html(&Renderer::default())
+                .unwrap()
+                .contains("<table>"),
+            "Front matter should be able to disable tables!"
+        );
+    }
 }
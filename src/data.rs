@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::vfs::SourceFs;
+
+/// Pages generated from the records of a data file, one content page per record, set on
+/// `config.toml` as `[[data_pages]]`. The record fields are used as the front matter, and its
+/// `content` field as the markdown.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct DataPages {
+    /// Path of the records on the data, e.g. `projects.project` for the `[[project]]` array of
+    /// `_data/projects.toml`
+    pub data: String,
+    /// The directory the pages are added to, it needs its own `_index.md` and `template.hbs`
+    pub section: String,
+    /// Template of every page, relative to the root e.g. `project.hbs`
+    pub template: String,
+}
+
+/// Data files of the site on the `_data/` directory, given to every template as `data`. Each
+/// file is keyed by its path without extension, e.g. `_data/team.toml` is `data.team` and
+/// `_data/projects/open.json` is `data.projects.open`.
+///
+/// The supported formats are TOML, JSON, YAML and CSV. A CSV file becomes an array of objects
+/// keyed by its header row, every value is kept as string.
+#[derive(Debug, Default, Serialize, PartialEq, Clone)]
+#[serde(transparent)]
+pub struct SiteData {
+    data: Map<String, Value>,
+}
+
+/// TOML dates are converted to string, instead of the private object used by `toml`.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => Value::from(float),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => array.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn parse_csv(raw_csv: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let mut reader = csv::Reader::from_reader(raw_csv.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+
+    for record in reader.records() {
+        let row: Map<String, Value> = headers
+            .iter()
+            .zip(record?.iter())
+            .map(|(header, value)| (header.to_owned(), Value::String(value.to_owned())))
+            .collect();
+
+        rows.push(Value::Object(row));
+    }
+
+    Ok(Value::Array(rows))
+}
+
+impl SiteData {
+    pub const DIR_NAME: &'static str = "_data";
+
+    /// Parse one data file by its extension, returns `None` if the format is not supported.
+    pub fn parse_file(
+        source_fs: &dyn SourceFs,
+        path: &Path,
+    ) -> Option<Result<Value, Box<dyn std::error::Error>>> {
+        let parse = |parser: fn(&str) -> Result<Value, Box<dyn std::error::Error>>| {
+            source_fs
+                .read_to_string(path)
+                .map_err(|err| err.into())
+                .and_then(|raw_data| parser(&raw_data))
+        };
+
+        match path.extension()?.to_str()? {
+            "toml" => Some(parse(|raw_data| {
+                Ok(toml_to_json(toml::from_str(raw_data)?))
+            })),
+            "json" => Some(parse(|raw_data| Ok(serde_json::from_str(raw_data)?))),
+            "yaml" | "yml" => Some(parse(|raw_data| Ok(serde_yaml::from_str(raw_data)?))),
+            "csv" => Some(parse(parse_csv)),
+            _ => None,
+        }
+    }
+
+    fn load_dir(
+        source_fs: &dyn SourceFs,
+        dir: &Path,
+        data: &mut Map<String, Value>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for entry in source_fs.read_dir(dir)? {
+            let entry_path = entry.path;
+
+            let Some(key) = entry_path.file_stem().and_then(|stem| stem.to_str()) else {
+                warn!(stage = "load", file:% = entry_path.display(); "[SiteData::load_dir] Cannot get data key of {entry_path:?}!");
+
+                continue;
+            };
+
+            let value = if entry.is_dir {
+                let mut dir_data = Map::new();
+                Self::load_dir(source_fs, &entry_path, &mut dir_data)?;
+
+                Value::Object(dir_data)
+            } else {
+                match Self::parse_file(source_fs, &entry_path) {
+                    Some(value) => {
+                        value.map_err(|err| format!("Cannot parse {entry_path:?}! {err}"))?
+                    }
+                    None => {
+                        warn!(stage = "load", file:% = entry_path.display(); "[SiteData::load_dir] Unsupported data file {entry_path:?}, skipped");
+
+                        continue;
+                    }
+                }
+            };
+
+            if data.contains_key(key) {
+                return Err(format!(
+                    "Data key '{key}' of {entry_path:?} is already used by another data file!"
+                )
+                .into());
+            }
+
+            debug!(stage = "load", file:% = entry_path.display(); "[SiteData::load_dir] Loaded data {entry_path:?}");
+            data.insert(key.to_owned(), value);
+        }
+
+        Ok(())
+    }
+
+    /// This function assume that `root_path` is the root of all of the pages, no `_data/`
+    /// directory means no data.
+    pub fn from_dir<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut site_data = Self::default();
+        let data_dir = root_path.as_ref().join(Self::DIR_NAME);
+
+        if source_fs.is_dir(&data_dir) {
+            Self::load_dir(source_fs, &data_dir, &mut site_data.data)?;
+        }
+
+        Ok(site_data)
+    }
+
+    /// Get a data by its path, nested keys are separated by `.` or `/`, e.g. `projects.open`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let mut keys = key.split(['.', '/']).filter(|key| !key.is_empty());
+        let first = self.data.get(keys.next()?)?;
+
+        keys.try_fold(first, |value, key| value.get(key))
+    }
+}
+
+#[cfg(test)]
+mod data_test {
+    use serde_json::json;
+
+    use crate::{data::SiteData, vfs::MemoryFs};
+
+    #[test]
+    fn data_files_loaded() {
+        let mut source_fs = MemoryFs::new()
+            .with_file(
+                &"_data/team.toml",
+                "[[members]]\nname = \"fauh45\"\njoined = 2024-05-03\n",
+            )
+            .with_file(&"_data/projects/open.json", r#"["simplistis"]"#)
+            .with_file(&"_data/links.yaml", "- title: Home\n  url: /\n")
+            .with_file(&"_data/papers.csv", "title,year\nA Paper,2023\n");
+
+        let site_data = SiteData::from_dir(&source_fs, &"").unwrap();
+
+        assert_eq!(
+            site_data.get("team.members").unwrap()[0],
+            json!({ "name": "fauh45", "joined": "2024-05-03" }),
+            "TOML date should be a string!"
+        );
+        assert_eq!(site_data.get("projects/open"), Some(&json!(["simplistis"])));
+        assert_eq!(site_data.get("links").unwrap()[0]["url"], "/");
+        assert_eq!(
+            site_data.get("papers"),
+            Some(&json!([{ "title": "A Paper", "year": "2023" }]))
+        );
+
+        source_fs.insert(&"_data/team.json", "{}");
+        assert!(
+            SiteData::from_dir(&source_fs, &"").is_err(),
+            "Duplicated data key should be an error!"
+        );
+    }
+}
@@ -0,0 +1,589 @@
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Write};
+
+use chrono::{DateTime, Utc};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, RenderErrorReason,
+    ScopedJson,
+};
+use log::warn;
+use pulldown_cmark::{html, Parser};
+use serde_json::{Map, Number, Value};
+
+use crate::config::SiteConfig;
+
+/// The params and hash of one helper call, already resolved to their values.
+struct HelperArgs<'a> {
+    name: &'a str,
+    params: Vec<Value>,
+    hash: BTreeMap<&'a str, Value>,
+}
+
+impl<'a> HelperArgs<'a> {
+    fn from_helper(helper: &'a Helper) -> Self {
+        Self {
+            name: helper.name(),
+            params: helper
+                .params()
+                .iter()
+                .map(|param| param.value().clone())
+                .collect(),
+            hash: helper
+                .hash()
+                .iter()
+                .map(|(key, value)| (*key, value.value().clone()))
+                .collect(),
+        }
+    }
+
+    fn param(&self, index: usize) -> Result<&Value, String> {
+        self.params
+            .get(index)
+            .ok_or_else(|| format!("Helper '{}' needs param at index {index}!", self.name))
+    }
+
+    fn param_str(&self, index: usize) -> Result<String, String> {
+        Ok(value_to_string(self.param(index)?))
+    }
+
+    fn param_array(&self, index: usize) -> Result<&Vec<Value>, String> {
+        match self.param(index)? {
+            Value::Array(array) => Ok(array),
+            _ => Err(format!(
+                "Helper '{}' needs an array at index {index}!",
+                self.name
+            )),
+        }
+    }
+
+    fn param_number(&self, index: usize) -> Result<f64, String> {
+        value_to_number(self.param(index)?)
+            .ok_or_else(|| format!("Helper '{}' needs a number at index {index}!", self.name))
+    }
+
+    fn hash_str(&self, key: &str) -> Option<String> {
+        self.hash.get(key).map(value_to_string)
+    }
+}
+
+/// A helper that returns a value, so it can be used both as `{{helper x}}` and as a
+/// subexpression e.g. `{{#each (limit posts 3)}}`.
+struct ValueHelper<F>(F);
+
+impl<F> HelperDef for ValueHelper<F>
+where
+    F: Fn(&HelperArgs) -> Result<Value, String> + Send + Sync,
+{
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        (self.0)(&HelperArgs::from_helper(helper))
+            .map(ScopedJson::Derived)
+            .map_err(|err| RenderErrorReason::Other(err).into())
+    }
+}
+
+/// `{{t "read_more"}}`, the UI text of the language of the page being rendered, see
+/// [`crate::i18n::LanguageConfig::strings`]. Falls back to the default language, then to the key
+/// itself. Hash values replace their placeholder, e.g. `{{t "posted_by" author=page.author}}`
+/// with `posted_by = "Posted by {author}"`.
+struct TranslateHelper {
+    strings: BTreeMap<String, Value>,
+    default_language: String,
+}
+
+impl HelperDef for TranslateHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        context: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let args = HelperArgs::from_helper(helper);
+        let key = args.param_str(0).map_err(RenderErrorReason::Other)?;
+        let lang = context
+            .data()
+            .get("lang")
+            .and_then(Value::as_str)
+            .unwrap_or(&self.default_language);
+
+        let text = [lang, self.default_language.as_str()]
+            .into_iter()
+            .find_map(|lang| {
+                self.strings
+                    .get(lang)
+                    .and_then(|strings| get_field(strings, &key).as_str())
+            });
+        let mut text = match text {
+            Some(text) => text.to_owned(),
+            None => {
+                warn!(stage = "render"; "[TranslateHelper::call_inner] No string '{key}' for language '{lang}'!");
+                key
+            }
+        };
+
+        for (name, value) in &args.hash {
+            text = text.replace(&format!("{{{name}}}"), &value_to_string(value));
+        }
+
+        Ok(ScopedJson::Derived(Value::String(text)))
+    }
+}
+
+/// Strings are taken as is, everything else is written as JSON.
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        _ => value.to_string(),
+    }
+}
+
+fn value_to_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Numbers are compared as numbers, everything else by its string, `null` is the last.
+fn compare_values(first: &Value, second: &Value) -> Ordering {
+    match (first, second) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ => match (value_to_number(first), value_to_number(second)) {
+            (Some(first), Some(second)) => first.total_cmp(&second),
+            _ => value_to_string(first).cmp(&value_to_string(second)),
+        },
+    }
+}
+
+/// Get a field of an object by its key, nested fields are separated by `.` e.g. `author.name`.
+fn get_field<'v>(value: &'v Value, key: &str) -> &'v Value {
+    key.split('.')
+        .try_fold(value, |value, key| value.get(key))
+        .unwrap_or(&Value::Null)
+}
+
+/// Whole numbers are returned as integer, so `{{add 1 2}}` is rendered as `3` instead of `3.0`.
+fn number_to_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Value::from(number as i64)
+    } else {
+        Number::from_f64(number).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn format_date(args: &HelperArgs) -> Result<Value, String> {
+    let date = match args.param_str(0)?.as_str() {
+        "now" => Utc::now(),
+        date => DateTime::parse_from_rfc3339(date)
+            .map_err(|err| format!("Cannot parse date '{date}'! Error: {err}"))?
+            .with_timezone(&Utc),
+    };
+    let format = match args.params.get(1) {
+        Some(format) => value_to_string(format),
+        None => args
+            .hash_str("format")
+            .unwrap_or_else(|| String::from("%Y-%m-%d")),
+    };
+
+    // Written instead of `to_string`, which panics on an invalid format
+    let mut formatted = String::new();
+    match args.hash_str("tz") {
+        Some(timezone) => {
+            let timezone: chrono_tz::Tz = timezone
+                .parse()
+                .map_err(|_| format!("Unknown timezone '{timezone}'!"))?;
+
+            write!(
+                formatted,
+                "{}",
+                date.with_timezone(&timezone).format(&format)
+            )
+        }
+        None => write!(formatted, "{}", date.format(&format)),
+    }
+    .map_err(|_| format!("Invalid date format '{format}'!"))?;
+
+    Ok(Value::String(formatted))
+}
+
+fn truncate(args: &HelperArgs) -> Result<Value, String> {
+    let text = args.param_str(0)?;
+    let length = args.param_number(1)? as usize;
+    let end = args.hash_str("end").unwrap_or_else(|| String::from("…"));
+
+    if text.chars().count() <= length {
+        return Ok(Value::String(text));
+    }
+
+    let mut truncated: String = text.chars().take(length).collect();
+    truncated.truncate(truncated.trim_end().len());
+    truncated.push_str(&end);
+
+    Ok(Value::String(truncated))
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut stripped = String::with_capacity(html.len());
+    let mut is_inside_tag = false;
+
+    for character in html.chars() {
+        match character {
+            '<' => is_inside_tag = true,
+            '>' if is_inside_tag => is_inside_tag = false,
+            _ if !is_inside_tag => stripped.push(character),
+            _ => {}
+        }
+    }
+
+    stripped
+}
+
+fn group_by(args: &HelperArgs) -> Result<Value, String> {
+    let key = args.param_str(1)?;
+    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+
+    for item in args.param_array(0)? {
+        let group_key = get_field(item, &key);
+
+        match groups
+            .iter_mut()
+            .find(|(existing, _)| existing == group_key)
+        {
+            Some((_, items)) => items.push(item.clone()),
+            None => groups.push((group_key.clone(), vec![item.clone()])),
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(group_key, items)| {
+            let mut group = Map::new();
+            group.insert("key".into(), group_key);
+            group.insert("items".into(), Value::Array(items));
+
+            Value::Object(group)
+        })
+        .collect())
+}
+
+fn sort_by(args: &HelperArgs) -> Result<Value, String> {
+    let key = args.param_str(1)?;
+    let mut items = args.param_array(0)?.clone();
+
+    items.sort_by(|first, second| compare_values(get_field(first, &key), get_field(second, &key)));
+    if args
+        .hash
+        .get("reverse")
+        .is_some_and(|reverse| reverse == true)
+    {
+        items.reverse();
+    }
+
+    Ok(Value::Array(items))
+}
+
+/// Keep the items where the field `key` equals the value, or contains it if the field is an
+/// array e.g. `(where posts "tags" "rust")`.
+fn where_equal(args: &HelperArgs) -> Result<Value, String> {
+    let key = args.param_str(1)?;
+    let expected = args.param(2)?;
+
+    Ok(args
+        .param_array(0)?
+        .iter()
+        .filter(|item| match get_field(item, &key) {
+            Value::Array(values) => values.contains(expected),
+            value => value == expected,
+        })
+        .cloned()
+        .collect())
+}
+
+fn concat(args: &HelperArgs) -> Result<Value, String> {
+    if args.params.iter().all(Value::is_array) {
+        return Ok(args
+            .params
+            .iter()
+            .filter_map(Value::as_array)
+            .flatten()
+            .cloned()
+            .collect());
+    }
+
+    Ok(Value::String(
+        args.params.iter().map(value_to_string).collect(),
+    ))
+}
+
+/// Register a helper that takes two numbers and returns a value.
+fn register_number_helper(
+    registry: &mut Handlebars,
+    name: &'static str,
+    operation: fn(f64, f64) -> Value,
+) {
+    registry.register_helper(
+        name,
+        Box::new(ValueHelper(move |args: &HelperArgs| {
+            Ok(operation(args.param_number(0)?, args.param_number(1)?))
+        })),
+    );
+}
+
+/// Register a helper that compares two values, numbers are compared as numbers.
+fn register_compare_helper(
+    registry: &mut Handlebars,
+    name: &'static str,
+    is_expected: fn(Ordering) -> bool,
+) {
+    registry.register_helper(
+        name,
+        Box::new(ValueHelper(move |args: &HelperArgs| {
+            Ok(Value::Bool(is_expected(compare_values(
+                args.param(0)?,
+                args.param(1)?,
+            ))))
+        })),
+    );
+}
+
+/// Register every built-in helper of simplistis on `registry`, some of them depend on `config`
+/// e.g. `absolute_url` uses `base_url`. Helpers that returns HTML (`markdownify`) should be
+/// used with triple braces, e.g. `{{{markdownify page.summary}}}`.
+pub fn register_helpers(registry: &mut Handlebars, config: &SiteConfig) {
+    registry.register_helper("date", Box::new(ValueHelper(format_date)));
+
+    let base_url = config.base_url.trim_end_matches('/').to_owned();
+    registry.register_helper(
+        "absolute_url",
+        Box::new(ValueHelper(move |args: &HelperArgs| {
+            let path = args.param_str(0)?;
+
+            Ok(Value::String(format!(
+                "{base_url}/{}",
+                path.trim_start_matches('/')
+            )))
+        })),
+    );
+
+    // The path of `base_url`, e.g. `/docs` for `https://example.com/docs/`
+    let base_path = config
+        .base_url
+        .split_once("://")
+        .map_or(config.base_url.as_str(), |(_, without_scheme)| {
+            without_scheme
+                .find('/')
+                .map_or("", |path_start| &without_scheme[path_start..])
+        })
+        .trim_end_matches('/')
+        .to_owned();
+    registry.register_helper(
+        "relative_url",
+        Box::new(ValueHelper(move |args: &HelperArgs| {
+            let path = args.param_str(0)?;
+
+            Ok(Value::String(format!(
+                "{base_path}/{}",
+                path.trim_start_matches('/')
+            )))
+        })),
+    );
+
+    registry.register_helper(
+        "t",
+        Box::new(TranslateHelper {
+            strings: config
+                .languages
+                .iter()
+                .map(|(lang, language)| (lang.clone(), Value::Object(language.strings.clone())))
+                .collect(),
+            default_language: config.default_language().to_owned(),
+        }),
+    );
+
+    registry.register_helper("truncate", Box::new(ValueHelper(truncate)));
+    registry.register_helper(
+        "striptags",
+        Box::new(ValueHelper(|args: &HelperArgs| {
+            Ok(Value::String(strip_tags(&args.param_str(0)?)))
+        })),
+    );
+
+    let markdown_options = config.markdown.to_options();
+    registry.register_helper(
+        "markdownify",
+        Box::new(ValueHelper(move |args: &HelperArgs| {
+            let markdown = args.param_str(0)?;
+            let mut html_output = String::new();
+            html::push_html(
+                &mut html_output,
+                Parser::new_ext(&markdown, markdown_options),
+            );
+
+            Ok(Value::String(html_output))
+        })),
+    );
+
+    let slugify_config = config.slugify.clone();
+    registry.register_helper(
+        "slugify",
+        Box::new(ValueHelper(move |args: &HelperArgs| {
+            Ok(Value::String(slugify_config.slugify(&args.param_str(0)?)))
+        })),
+    );
+
+    registry.register_helper("group_by", Box::new(ValueHelper(group_by)));
+    registry.register_helper("sort_by", Box::new(ValueHelper(sort_by)));
+    registry.register_helper("where", Box::new(ValueHelper(where_equal)));
+    registry.register_helper(
+        "limit",
+        Box::new(ValueHelper(|args: &HelperArgs| {
+            let limit = args.param_number(1)? as usize;
+
+            Ok(args.param_array(0)?.iter().take(limit).cloned().collect())
+        })),
+    );
+    registry.register_helper(
+        "json",
+        Box::new(ValueHelper(|args: &HelperArgs| {
+            let value = args.param(0)?;
+            let json = if args.hash.get("pretty").is_some_and(|pretty| pretty == true) {
+                serde_json::to_string_pretty(value)
+            } else {
+                serde_json::to_string(value)
+            };
+
+            json.map(Value::String).map_err(|err| err.to_string())
+        })),
+    );
+    registry.register_helper("concat", Box::new(ValueHelper(concat)));
+
+    register_number_helper(registry, "add", |first, second| {
+        number_to_value(first + second)
+    });
+    register_number_helper(registry, "sub", |first, second| {
+        number_to_value(first - second)
+    });
+    register_number_helper(registry, "mul", |first, second| {
+        number_to_value(first * second)
+    });
+    register_number_helper(registry, "div", |first, second| {
+        number_to_value(first / second)
+    });
+    register_number_helper(registry, "mod", |first, second| {
+        number_to_value(first % second)
+    });
+
+    // Replace the integer only comparison of Handlebars, `eq` and `ne` are kept as is
+    register_compare_helper(registry, "gt", Ordering::is_gt);
+    register_compare_helper(registry, "gte", Ordering::is_ge);
+    register_compare_helper(registry, "lt", Ordering::is_lt);
+    register_compare_helper(registry, "lte", Ordering::is_le);
+}
+
+#[cfg(test)]
+mod helpers_test {
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use crate::{config::SiteConfig, helpers::register_helpers};
+
+    fn render(template: &str, data: &serde_json::Value) -> String {
+        let config = SiteConfig {
+            base_url: "https://example.com/docs/".into(),
+            ..Default::default()
+        };
+        let mut registry = Handlebars::new();
+        register_helpers(&mut registry, &config);
+
+        registry.render_template(template, data).unwrap()
+    }
+
+    #[test]
+    fn text_helpers_rendered() {
+        let data = json!({ "date": "2024-05-03T20:00:00Z", "title": "Hello World!" });
+
+        assert_eq!(
+            render(r#"{{date date "%d %B %Y" tz="Asia/Jakarta"}}"#, &data),
+            "04 May 2024"
+        );
+        assert_eq!(render("{{truncate title 5}}", &data), "Hello…");
+        assert_eq!(render("{{slugify title}}", &data), "hello-world");
+        assert_eq!(
+            render(
+                r#"{{absolute_url "/blog/"}} {{relative_url "blog/"}}"#,
+                &data
+            ),
+            "https://example.com/docs/blog/ /docs/blog/"
+        );
+        assert_eq!(
+            render(r#"{{{markdownify "**hi**"}}}"#, &data),
+            "<p><strong>hi</strong></p>\n"
+        );
+        assert_eq!(render(r#"{{striptags "<b>bold</b>"}}"#, &data), "bold");
+        assert_eq!(render("{{add 1 (mul 2 3)}} {{div 1 4}}", &data), "7 0.25");
+    }
+
+    #[test]
+    fn invalid_date_format_errors() {
+        let data = json!({ "date": "2024-05-03T20:00:00Z" });
+        let mut registry = Handlebars::new();
+        register_helpers(&mut registry, &SiteConfig::default());
+
+        for template in [
+            r#"{{date date "%Q"}}"#,
+            r#"{{date date "%Q" tz="Asia/Jakarta"}}"#,
+        ] {
+            assert!(
+                registry.render_template(template, &data).is_err(),
+                "Invalid date format should be a render error!"
+            );
+        }
+    }
+
+    #[test]
+    fn collection_helpers_rendered() {
+        let data = json!({
+            "posts": [
+                { "title": "B", "author": "x", "tags": ["rust"], "weight": 2 },
+                { "title": "A", "author": "y", "tags": ["web"], "weight": 10 },
+                { "title": "C", "author": "x", "tags": ["rust", "web"], "weight": 1 },
+            ]
+        });
+
+        assert_eq!(
+            render(
+                r#"{{#each (limit (sort_by posts "weight") 2)}}{{title}}{{/each}}"#,
+                &data
+            ),
+            "CB",
+            "Posts should be sorted by numeric weight!"
+        );
+        assert_eq!(
+            render(
+                r#"{{#each (group_by posts "author")}}{{key}}:{{len items}} {{/each}}"#,
+                &data
+            ),
+            "x:2 y:1 "
+        );
+        assert_eq!(
+            render(
+                r#"{{#each (where posts "tags" "web")}}{{title}}{{/each}}"#,
+                &data
+            ),
+            "AC"
+        );
+        assert_eq!(
+            render(r#"{{#if (gt 10 9.5)}}yes{{/if}} {{concat "a" "b"}}"#, &data),
+            "yes ab"
+        );
+    }
+}
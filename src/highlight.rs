@@ -0,0 +1,362 @@
+use std::{fmt::Write, sync::OnceLock};
+
+use log::warn;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    html::{
+        css_for_theme_with_class_style, line_tokens_to_classed_spans,
+        start_highlighted_html_snippet, styled_line_to_highlighted_html, ClassStyle,
+        IncludeBackground,
+    },
+    parsing::{ParseState, ScopeStack, ScopeStackOp, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+/// Build time syntax highlighting of fenced code blocks, set on `config.toml` as
+/// `[markdown.highlight]`.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct HighlightConfig {
+    pub enabled: bool,
+    /// One of the syntect default theme names, e.g. `base16-ocean.dark` or `InspiredGitHub`
+    pub theme: String,
+    /// Output CSS classes instead of inline styles, the stylesheet for `theme` will be written
+    /// to [`HighlightConfig::CSS_FILE_NAME`] at the output root.
+    pub css_classes: bool,
+    pub line_numbers: bool,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            theme: "base16-ocean.dark".into(),
+            css_classes: false,
+            line_numbers: false,
+        }
+    }
+}
+
+impl HighlightConfig {
+    pub const CSS_FILE_NAME: &'static str = "highlight.css";
+
+    pub fn get_theme(&self) -> Option<&'static Theme> {
+        theme_set().themes.get(&self.theme)
+    }
+
+    /// Render the class based stylesheet of the configured theme, only if `css_classes` is used.
+    pub fn render_css(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        if !self.enabled || !self.css_classes {
+            return Ok(None);
+        }
+
+        let Some(theme) = self.get_theme() else {
+            return Err(format!("Unknown highlight theme '{}'!", self.theme).into());
+        };
+
+        Ok(Some(css_for_theme_with_class_style(
+            theme,
+            ClassStyle::Spaced,
+        )?))
+    }
+}
+
+/// Options that can be given on the fenced code block info string, after the language, written
+/// as a TOML inline table e.g. ```` ```rust {hl_lines=[2,3], linenos=true} ````.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct CodeBlockOptions {
+    pub hl_lines: Vec<usize>,
+    pub linenos: Option<bool>,
+}
+
+impl CodeBlockOptions {
+    /// Split the info string into the language token and the options, invalid options are
+    /// ignored so the code block would still be rendered.
+    pub fn from_info_string(info: &str) -> (String, Self) {
+        let info = info.trim();
+
+        let Some(options_start) = info.find('{') else {
+            return (info.to_owned(), Self::default());
+        };
+
+        let language = info[..options_start].trim().to_owned();
+        let raw_options = format!("options = {}", info[options_start..].trim());
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            options: CodeBlockOptions,
+        }
+
+        match toml::from_str::<Wrapper>(&raw_options) {
+            Ok(wrapper) => (language, wrapper.options),
+            Err(err) => {
+                warn!(stage = "render"; "[CodeBlockOptions::from_info_string] Invalid code block options '{info}'! Error: {err}");
+
+                (language, Self::default())
+            }
+        }
+    }
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+pub(crate) fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns the opening `<span>` tags of every scope in `stack`, this is needed as the classed
+/// spans could be left open at the end of a line, while each line is wrapped on its own element.
+fn reopen_classed_spans(stack: &ScopeStack) -> Result<String, syntect::Error> {
+    let ops: Vec<(usize, ScopeStackOp)> = stack
+        .as_slice()
+        .iter()
+        .map(|scope| (0, ScopeStackOp::Push(*scope)))
+        .collect();
+
+    let (spans, _) =
+        line_tokens_to_classed_spans("", &ops, ClassStyle::Spaced, &mut ScopeStack::new())?;
+
+    Ok(spans)
+}
+
+fn highlight_lines(
+    code: &str,
+    syntax: &SyntaxReference,
+    config: &HighlightConfig,
+    theme: &Theme,
+) -> Result<Vec<String>, syntect::Error> {
+    let mut lines = Vec::new();
+
+    if config.css_classes {
+        let mut parse_state = ParseState::new(syntax);
+        let mut scope_stack = ScopeStack::new();
+
+        for line in LinesWithEndings::from(code) {
+            let ops = parse_state.parse_line(line, syntax_set())?;
+            let mut line_html = reopen_classed_spans(&scope_stack)?;
+            let open_spans = scope_stack.len() as isize;
+
+            let (spans, delta) =
+                line_tokens_to_classed_spans(line, &ops, ClassStyle::Spaced, &mut scope_stack)?;
+            line_html.push_str(&spans);
+
+            for _ in 0..(open_spans + delta) {
+                line_html.push_str("</span>");
+            }
+
+            lines.push(line_html);
+        }
+    } else {
+        let mut highlighter = HighlightLines::new(syntax, theme);
+
+        for line in LinesWithEndings::from(code) {
+            let regions = highlighter.highlight_line(line, syntax_set())?;
+            lines.push(styled_line_to_highlighted_html(
+                &regions,
+                IncludeBackground::No,
+            )?);
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Render one fenced code block to HTML, returns `None` if it cannot be highlighted so the
+/// caller can fall back to the default pulldown-cmark output.
+pub fn highlight_code_block(info: &str, code: &str, config: &HighlightConfig) -> Option<String> {
+    let Some(theme) = config.get_theme() else {
+        warn!(
+            stage = "render";
+            "[highlight::highlight_code_block] Unknown highlight theme '{}'!",
+            config.theme
+        );
+
+        return None;
+    };
+
+    let (language, options) = CodeBlockOptions::from_info_string(info);
+    let syntax = syntax_set()
+        .find_syntax_by_token(&language)
+        .unwrap_or_else(|| syntax_set().find_syntax_plain_text());
+
+    let lines = match highlight_lines(code, syntax, config, theme) {
+        Ok(lines) => lines,
+        Err(err) => {
+            warn!(stage = "render"; "[highlight::highlight_code_block] Failed highlighting '{language}' code block! Error: {err}");
+
+            return None;
+        }
+    };
+
+    let line_numbers = options.linenos.unwrap_or(config.line_numbers);
+    let mut html = if config.css_classes {
+        String::from("<pre class=\"highlight\">")
+    } else {
+        // Snippet start comes with a trailing new line that would show up inside the `<pre>`
+        start_highlighted_html_snippet(theme).0.trim_end().replacen(
+            "<pre ",
+            "<pre class=\"highlight\" ",
+            1,
+        )
+    };
+
+    if language.is_empty() {
+        html.push_str("<code>");
+    } else {
+        let language = escape_attribute(&language);
+        let _ = write!(
+            html,
+            "<code class=\"language-{language}\" data-lang=\"{language}\">"
+        );
+    }
+
+    for (index, line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+
+        if options.hl_lines.contains(&line_number) {
+            html.push_str("<span class=\"line hl\">");
+        } else {
+            html.push_str("<span class=\"line\">");
+        }
+
+        if line_numbers {
+            let _ = write!(html, "<span class=\"line-number\">{line_number}</span>");
+        }
+
+        html.push_str(line);
+        html.push_str("</span>");
+    }
+
+    html.push_str("</code></pre>\n");
+
+    Some(html)
+}
+
+/// Walk the markdown event stream, replacing every fenced code block with its highlighted HTML.
+pub fn highlight_code_blocks<'a, I: Iterator<Item = Event<'a>>>(
+    events: I,
+    config: &HighlightConfig,
+) -> Vec<Event<'a>> {
+    let mut output = Vec::new();
+    let mut current_block: Option<(CowStr<'a>, String, Vec<Event<'a>>)> = None;
+
+    for event in events {
+        match (&mut current_block, event) {
+            (None, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))) => {
+                let start_event = Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info.clone())));
+                current_block = Some((info, String::new(), vec![start_event]));
+            }
+            (Some((_, code, raw_events)), Event::Text(text)) => {
+                code.push_str(&text);
+                raw_events.push(Event::Text(text));
+            }
+            (Some(_), Event::End(TagEnd::CodeBlock)) => {
+                let (info, code, mut raw_events) = current_block.take().unwrap();
+
+                match highlight_code_block(&info, &code, config) {
+                    Some(html) => output.push(Event::Html(html.into())),
+                    None => {
+                        raw_events.push(Event::End(TagEnd::CodeBlock));
+                        output.append(&mut raw_events);
+                    }
+                }
+            }
+            (Some((_, _, raw_events)), event) => raw_events.push(event),
+            (None, event) => output.push(event),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod highlight_test {
+    use pulldown_cmark::{html, Parser};
+
+    use crate::highlight::{highlight_code_blocks, CodeBlockOptions, HighlightConfig};
+
+    fn render(markdown: &str, config: &HighlightConfig) -> String {
+        let mut html_output = String::new();
+        html::push_html(
+            &mut html_output,
+            highlight_code_blocks(Parser::new(markdown), config).into_iter(),
+        );
+
+        html_output
+    }
+
+    #[test]
+    fn info_string_options_parsed() {
+        let (language, options) =
+            CodeBlockOptions::from_info_string("rust {hl_lines=[2,3], linenos=true}");
+
+        assert_eq!(language, "rust", "Language should be the first token!");
+        assert_eq!(options.hl_lines, vec![2, 3], "Failed to parse hl_lines!");
+        assert_eq!(options.linenos, Some(true), "Failed to parse linenos!");
+    }
+
+    #[test]
+    fn code_block_highlighted() {
+        let config = HighlightConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let output = render(
+            "```rust {hl_lines=[2]}\nfn main() {\n    let a = 1;\n}\n```",
+            &config,
+        );
+
+        assert!(
+            output.contains("<pre class=\"highlight\" style="),
+            "Theme output should use inline styles!"
+        );
+        assert_eq!(
+            output.matches("<span class=\"line hl\">").count(),
+            1,
+            "Exactly one line should be highlighted!"
+        );
+    }
+
+    #[test]
+    fn code_block_css_classes() {
+        let config = HighlightConfig {
+            enabled: true,
+            css_classes: true,
+            line_numbers: true,
+            ..Default::default()
+        };
+        let output = render("```rust\n/* multi\nline */\n```", &config);
+
+        assert!(
+            !output.contains("style="),
+            "Class output should not use inline styles!"
+        );
+        assert_eq!(
+            output.matches("<span").count(),
+            output.matches("</span>").count(),
+            "Every line should close its own spans!"
+        );
+        assert!(
+            output.contains("<span class=\"line-number\">2</span>"),
+            "Line numbers should be rendered!"
+        );
+    }
+}
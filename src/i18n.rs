@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{config::SiteConfig, links::normalize_path, pages::Page};
+
+/// One language of the site, set on `config.toml` as e.g. `[languages.id]`.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct LanguageConfig {
+    /// Display name e.g. `Bahasa Indonesia`, defaults to the language code
+    pub name: Option<String>,
+    /// The page path the pages of this language are put under, defaults to the language code.
+    /// Not used by the default language, which is always on the root
+    pub prefix: Option<String>,
+    /// UI text of the templates used by the `t` helper, e.g. `read_more = "Baca selengkapnya"`,
+    /// nested tables are looked up with `.` e.g. `{{t "nav.home"}}`
+    pub strings: Map<String, Value>,
+}
+
+/// Another language version of a page, given to the templates as `translations`.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct Translation {
+    pub lang: String,
+    pub name: String,
+    pub url: String,
+}
+
+/// The source path shared by every translation of a content, which is the path without the
+/// language suffix e.g. `blog/post.md` for `blog/post.id.md`.
+pub fn translation_key(source_path: &Path, config: &SiteConfig) -> PathBuf {
+    let source_path = normalize_path(source_path);
+    let Some(lang) = config.content_language(&source_path) else {
+        return source_path;
+    };
+
+    // Safe to unwrap as the language is taken from the file stem
+    let file_stem = source_path.file_stem().unwrap().to_string_lossy();
+    let file_stem = file_stem
+        .strip_suffix(&format!(".{lang}"))
+        .unwrap_or(&file_stem);
+    let file_name = match source_path.extension() {
+        Some(extension) => format!("{file_stem}.{}", extension.to_string_lossy()),
+        None => file_stem.to_owned(),
+    };
+
+    source_path.with_file_name(file_name)
+}
+
+/// The translation key of `page`, set when the page is loaded by [`Page::from_dir`].
+fn page_translation_key(page: &Page, config: &SiteConfig) -> PathBuf {
+    page.content
+        .metadata
+        .translation_key
+        .clone()
+        .unwrap_or_else(|| translation_key(&page.content.source_path, config))
+}
+
+/// Every language version of the pages, keyed by their [`translation_key`].
+#[derive(Debug, Default, Clone)]
+pub struct Translations {
+    pages: HashMap<PathBuf, Vec<Translation>>,
+}
+
+impl Translations {
+    /// This function assume that `root_page` is the root of all of the pages, pages without
+    /// language (e.g. added from data files) have no translations.
+    pub fn from_page(root_page: &Page, config: &SiteConfig) -> Self {
+        let mut pages = HashMap::<PathBuf, Vec<Translation>>::new();
+
+        for page in root_page.all_pages() {
+            let Some(lang) = &page.content.metadata.lang else {
+                continue;
+            };
+
+            pages
+                .entry(page_translation_key(page, config))
+                .or_default()
+                .push(Translation {
+                    lang: lang.clone(),
+                    name: config.language_name(lang),
+                    url: page.url(),
+                });
+        }
+
+        for translations in pages.values_mut() {
+            translations.sort_by(|first, second| first.lang.cmp(&second.lang));
+        }
+
+        Self { pages }
+    }
+
+    /// The other language versions of `page`, without itself.
+    pub fn for_page(&self, page: &Page, config: &SiteConfig) -> Vec<Translation> {
+        let Some(lang) = &page.content.metadata.lang else {
+            return vec![];
+        };
+
+        self.pages
+            .get(&page_translation_key(page, config))
+            .map(|translations| {
+                translations
+                    .iter()
+                    .filter(|translation| &translation.lang != lang)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod i18n_test {
+    use std::path::Path;
+
+    use crate::{
+        report::SkippedFile,
+        site::Site,
+        vfs::{MemoryFs, SourceFs},
+    };
+
+    const CONFIG: &str = r#"
+base_url = "https://example.com/"
+sitemap = true
+
+[languages.en]
+name = "English"
+strings = { read_more = "Read more" }
+
+[languages.id]
+name = "Bahasa Indonesia"
+strings = { read_more = "Baca selengkapnya" }
+"#;
+    const TEMPLATE: &str =
+        "{{lang}} {{page.title}} {{t \"read_more\"}}{{#each translations}} {{lang}}={{url}}{{/each}}";
+
+    #[test]
+    fn translations_rendered() {
+        let source_fs = MemoryFs::new()
+            .with_file(&"config.toml", CONFIG)
+            .with_file(&"_index.md", "Home")
+            .with_file(&"_index.id.md", "Beranda")
+            .with_file(&"template.hbs", TEMPLATE)
+            .with_file(&"blog/_index.md", "Blog")
+            .with_file(&"blog/_index.id.md", "Blog")
+            .with_file(&"blog/template.hbs", TEMPLATE)
+            .with_file(&"blog/content.hbs", TEMPLATE)
+            .with_file(&"blog/post.md", "Hello")
+            .with_file(
+                &"blog/post.id.md",
+                "---\nupdated_at = 2024-05-03T00:00:00Z\n---\nHalo",
+            )
+            .with_file(&"blog/english-only.md", "English")
+            .with_file(&"blog/tree.md", "Tree")
+            .with_file(&"blog/_index.fr.md", "Bonjour")
+            .with_file(&"id/blog/tree.md", "Pohon")
+            .with_file(&"notes/_index.md", "Notes")
+            .with_file(&"notes/template.hbs", TEMPLATE)
+            .with_file(&"notes/content.hbs", TEMPLATE)
+            .with_file(&"notes/catatan.id.md", "Catatan");
+
+        let site = Site::builder().source_fs(source_fs).build().unwrap();
+        let mut output_fs = MemoryFs::new();
+        let report = site.render_to_sink(&mut output_fs).unwrap();
+
+        assert_eq!(
+            output_fs
+                .read_to_string(Path::new("id/blog/post/index.html"))
+                .unwrap(),
+            "id post Baca selengkapnya en=/blog/post/",
+            "Translation should be under the language prefix without the suffix on its slug!"
+        );
+        assert_eq!(
+            output_fs
+                .read_to_string(Path::new("blog/post/index.html"))
+                .unwrap(),
+            "en post Read more id=/id/blog/post/"
+        );
+        assert_eq!(
+            output_fs
+                .read_to_string(Path::new("blog/english-only/index.html"))
+                .unwrap(),
+            "en english-only Read more",
+            "Untranslated page should have no translations!"
+        );
+        assert_eq!(
+            output_fs
+                .read_to_string(Path::new("id/blog/tree/index.html"))
+                .unwrap(),
+            "id tree Baca selengkapnya en=/blog/tree/",
+            "Language subtree should be a translation of the same path!"
+        );
+        assert!(output_fs.is_file(Path::new("id/index.html")));
+        assert!(
+            output_fs.is_file(Path::new("blog/index-fr/index.html")),
+            "Index of a language not on languages should be a content!"
+        );
+        assert_eq!(
+            report.skipped,
+            vec![SkippedFile::new(
+                &"notes/catatan.id.md",
+                "no _index.id.md on its directory"
+            )]
+        );
+
+        let sitemap = output_fs
+            .read_to_string(Path::new("id/sitemap.xml"))
+            .unwrap();
+        assert!(
+            sitemap.contains("<loc>https://example.com/id/blog/post/</loc>"),
+            "Sitemap should list the pages of its language!"
+        );
+        assert!(
+            sitemap.contains(
+                r#"<xhtml:link rel="alternate" hreflang="en" href="https://example.com/blog/post/"/>"#
+            ),
+            "Sitemap should link the other languages!"
+        );
+        assert!(!sitemap.contains("english-only"));
+        assert!(
+            sitemap.contains(
+                "<loc>https://example.com/id/blog/post/</loc>\n    <lastmod>2024-05-03</lastmod>"
+            ),
+            "Dated page should have its lastmod!"
+        );
+        assert!(
+            sitemap
+                .split("<url>")
+                .find(|url| url.contains("<loc>https://example.com/id/blog/tree/</loc>"))
+                .is_some_and(|url| !url.contains("<lastmod>")),
+            "Undated page should have no lastmod!"
+        );
+    }
+}
@@ -1,3 +1,24 @@
+pub mod config;
 pub mod content;
+pub mod data;
+pub mod helpers;
+pub mod highlight;
+pub mod i18n;
+pub mod link_checker;
+pub mod links;
+pub mod menu;
+pub mod navigation;
 pub mod pages;
+pub mod permalink;
+pub mod plugin;
 pub mod preparser;
+pub mod redirect;
+pub mod renderer;
+pub mod report;
+pub mod script_helper;
+pub mod shortcode;
+pub mod site;
+pub mod sitemap;
+pub mod slugify;
+pub mod toc;
+pub mod vfs;
@@ -0,0 +1,417 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{links::normalize_path, pages::Page, vfs::SourceFs};
+
+/// A link on a generated HTML file that points to a missing file or anchor.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrokenLink {
+    /// The HTML file containing the link, relative to the output root
+    pub output_file: PathBuf,
+    /// The content file that produced `output_file`, if it's produced by one
+    pub source_file: Option<PathBuf>,
+    pub link: String,
+    pub reason: String,
+}
+
+/// A link to another site, these are only listed and never fetched.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExternalLink {
+    pub output_file: PathBuf,
+    pub link: String,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct LinkCheckReport {
+    pub checked_files: usize,
+    pub broken: Vec<BrokenLink>,
+    pub external: Vec<ExternalLink>,
+}
+
+/// Attributes of one HTML tag that matters to the link checker.
+#[derive(Debug, Default)]
+struct HtmlFileLinks {
+    links: Vec<String>,
+    ids: HashSet<String>,
+}
+
+/// Parse the attributes of a tag, `tag` is everything between `<` and `>` excluding both.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    // Skip the tag name
+    let mut remaining = tag
+        .trim_start()
+        .trim_start_matches(|character: char| !character.is_whitespace());
+
+    loop {
+        remaining = remaining.trim_start();
+        if remaining.is_empty() {
+            break;
+        }
+
+        let name_end = remaining
+            .find(|character: char| character == '=' || character.is_whitespace())
+            .unwrap_or(remaining.len());
+        let name = remaining[..name_end].to_ascii_lowercase();
+        remaining = remaining[name_end..].trim_start();
+
+        let Some(value_part) = remaining.strip_prefix('=') else {
+            // Attribute without value e.g. `hidden`
+            continue;
+        };
+        let value_part = value_part.trim_start();
+
+        let (value, rest) = match value_part.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let quoted = &value_part[1..];
+                let value_end = quoted.find(quote).unwrap_or(quoted.len());
+
+                (
+                    &quoted[..value_end],
+                    quoted.get(value_end + 1..).unwrap_or(""),
+                )
+            }
+            _ => {
+                let value_end = value_part
+                    .find(char::is_whitespace)
+                    .unwrap_or(value_part.len());
+
+                (&value_part[..value_end], &value_part[value_end..])
+            }
+        };
+
+        attributes.push((name, value.replace("&amp;", "&")));
+        remaining = rest;
+    }
+
+    attributes
+}
+
+impl HtmlFileLinks {
+    fn from_html(html: &str) -> Self {
+        let mut file_links = Self::default();
+        let mut remaining = html;
+
+        while let Some(tag_start) = remaining.find('<') {
+            remaining = &remaining[tag_start + 1..];
+
+            if let Some(comment) = remaining.strip_prefix("!--") {
+                remaining = comment
+                    .find("-->")
+                    .map(|comment_end| &comment[comment_end + 3..])
+                    .unwrap_or("");
+                continue;
+            }
+
+            // Find the end of the tag while ignoring `>` inside quoted attribute value
+            let mut quote = None;
+            let tag_end = remaining
+                .char_indices()
+                .find(|(_, character)| match (quote, character) {
+                    (None, '"' | '\'') => {
+                        quote = Some(*character);
+                        false
+                    }
+                    (Some(open_quote), character) if open_quote == *character => {
+                        quote = None;
+                        false
+                    }
+                    (None, '>') => true,
+                    _ => false,
+                })
+                .map(|(index, _)| index)
+                .unwrap_or(remaining.len());
+
+            let tag = &remaining[..tag_end];
+            remaining = remaining.get(tag_end + 1..).unwrap_or("");
+
+            if tag.starts_with('/') || tag.starts_with('!') {
+                continue;
+            }
+
+            for (name, value) in parse_attributes(tag) {
+                match name.as_str() {
+                    "href" | "src" => file_links.links.push(value),
+                    "id" | "name" => {
+                        file_links.ids.insert(value);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        file_links
+    }
+}
+
+/// Returns the map of every output file, relative to the output root, to the content file that
+/// produced it.
+pub fn output_sources(root_page: &Page) -> HashMap<PathBuf, PathBuf> {
+    root_page
+        .all_pages()
+        .into_iter()
+        .map(|page| (page.output_file(), page.content.source_path.clone()))
+        .collect()
+}
+
+fn collect_html_files(
+    output_fs: &dyn SourceFs,
+    dir: &Path,
+    html_files: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    for entry in output_fs.read_dir(dir)? {
+        let entry_path = entry.path;
+
+        if entry.is_dir {
+            collect_html_files(output_fs, &entry_path, html_files)?;
+        } else if entry_path
+            .extension()
+            .is_some_and(|extension| extension == "html")
+        {
+            html_files.push(entry_path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_external_link(link: &str) -> bool {
+    if link.starts_with("//") {
+        return true;
+    }
+
+    // Any scheme e.g. `https:`, `mailto:`
+    link.split_once(':').is_some_and(|(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || "+-.".contains(character))
+    })
+}
+
+/// Decode the `%XX` escapes of a link, e.g. the non-ASCII characters escaped by the markdown
+/// renderer. Invalid escapes are kept as is.
+fn percent_decode(link: &str) -> String {
+    let bytes = link.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+struct LinkChecker<'a> {
+    output_fs: &'a dyn SourceFs,
+    output_dir: &'a Path,
+    parsed_files: HashMap<PathBuf, HtmlFileLinks>,
+}
+
+impl<'a> LinkChecker<'a> {
+    fn get_file_links(&mut self, file: &Path) -> std::io::Result<&HtmlFileLinks> {
+        if !self.parsed_files.contains_key(file) {
+            let html = self.output_fs.read_to_string(&self.output_dir.join(file))?;
+            self.parsed_files
+                .insert(file.to_path_buf(), HtmlFileLinks::from_html(&html));
+        }
+
+        Ok(&self.parsed_files[file])
+    }
+
+    /// Returns why `link` on the HTML file `current_file` is broken, if it is.
+    fn check_link(&mut self, current_file: &Path, link: &str) -> Option<String> {
+        let (link_path, fragment) = match link.split_once('#') {
+            Some((link_path, fragment)) => (link_path, Some(fragment)),
+            None => (link, None),
+        };
+        let link_path = percent_decode(link_path.split('?').next().unwrap_or(""));
+        let fragment = fragment.map(percent_decode);
+
+        let target_file = if link_path.is_empty() {
+            current_file.to_path_buf()
+        } else {
+            let target_path = match link_path.strip_prefix('/') {
+                Some(root_path) => PathBuf::from(root_path),
+                None => current_file
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(&link_path),
+            };
+            let mut target_file = normalize_path(&target_path);
+
+            if link_path.ends_with('/')
+                || self.output_fs.is_dir(&self.output_dir.join(&target_file))
+            {
+                target_file.push("index.html");
+            }
+
+            if !self.output_fs.is_file(&self.output_dir.join(&target_file)) {
+                return Some(format!("{target_file:?} does not exist"));
+            }
+
+            target_file
+        };
+
+        let fragment = fragment.filter(|fragment| !fragment.is_empty())?;
+        if target_file
+            .extension()
+            .is_some_and(|extension| extension == "html")
+        {
+            match self.get_file_links(&target_file) {
+                Ok(target_links) if !target_links.ids.contains(&fragment) => {
+                    return Some(format!("{target_file:?} has no '#{fragment}' anchor"));
+                }
+                Err(err) => return Some(format!("Cannot read {target_file:?}! Error: {err}")),
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+/// Check every internal link and anchor of the HTML files on `output_dir`, against the files
+/// on `output_dir` itself, both read from `output_fs`. `sources` is used to report which content
+/// produced the broken link, see [`output_sources`].
+pub fn check_links<P: AsRef<Path>>(
+    output_fs: &dyn SourceFs,
+    output_dir: &P,
+    sources: &HashMap<PathBuf, PathBuf>,
+) -> std::io::Result<LinkCheckReport> {
+    let output_dir = output_dir.as_ref();
+    let mut report = LinkCheckReport::default();
+
+    let mut html_files = Vec::new();
+    collect_html_files(output_fs, output_dir, &mut html_files)?;
+    html_files.sort();
+
+    let mut checker = LinkChecker {
+        output_fs,
+        output_dir,
+        parsed_files: HashMap::new(),
+    };
+
+    for html_file in html_files {
+        // Safe to unwrap as all of the files are collected from inside `output_dir`
+        let relative_file = html_file.strip_prefix(output_dir).unwrap().to_path_buf();
+        let links = checker.get_file_links(&relative_file)?.links.clone();
+        report.checked_files += 1;
+
+        for link in links {
+            if is_external_link(&link) {
+                report.external.push(ExternalLink {
+                    output_file: relative_file.clone(),
+                    link,
+                });
+
+                continue;
+            }
+
+            if let Some(reason) = checker.check_link(&relative_file, &link) {
+                report.broken.push(BrokenLink {
+                    output_file: relative_file.clone(),
+                    source_file: sources.get(&relative_file).cloned(),
+                    link,
+                    reason,
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod link_checker_test {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use crate::{
+        link_checker::{check_links, HtmlFileLinks},
+        vfs::MemoryFs,
+    };
+
+    #[test]
+    fn html_attributes_parsed() {
+        let html = r#"<h2 id="how">How?</h2><!-- <a href="/commented"> --><a class='x' href='/blog/#how'>a > b</a><img src=cat.png alt="x">"#;
+
+        let file_links = HtmlFileLinks::from_html(html);
+
+        assert_eq!(file_links.links, vec!["/blog/#how", "cat.png"]);
+        assert!(file_links.ids.contains("how"), "Failed to parse id!");
+    }
+
+    #[test]
+    fn broken_links_reported() {
+        let output_fs = MemoryFs::new()
+            .with_file(
+                &"public/index.html",
+                r##"<a href="/blog/">ok</a><a href="/blog/#intro">ok</a><a href="/missing/">broken</a><a href="blog/#nope">broken</a><a href="https://example.com">external</a>"##,
+            )
+            .with_file(
+                &"public/blog/index.html",
+                r#"<h1 id="intro">Intro</h1><a href="../">ok</a>"#,
+            );
+
+        let sources = HashMap::from([(PathBuf::from("index.html"), PathBuf::from("_index.md"))]);
+        let report = check_links(&output_fs, &"public", &sources).unwrap();
+
+        assert_eq!(report.checked_files, 2);
+        assert_eq!(report.external.len(), 1, "External link should be listed!");
+        assert_eq!(
+            report
+                .broken
+                .iter()
+                .map(|broken| broken.link.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/missing/", "blog/#nope"]
+        );
+        assert_eq!(
+            report.broken[0].source_file,
+            Some(PathBuf::from("_index.md")),
+            "Broken link should be reported with its source file!"
+        );
+    }
+
+    #[test]
+    fn encoded_links_decoded() {
+        let output_fs = MemoryFs::new()
+            .with_file(
+                &"public/index.html",
+                r##"<a href="/blog/%C3%BCber-uns/">ok</a><a href="/blog/%C3%BCber-uns/#%C3%BCber">ok</a><a href="/blog/%C3%BCber-alles/">broken</a>"##,
+            )
+            .with_file(
+                &"public/blog/über-uns/index.html",
+                r#"<h1 id="über">Über</h1>"#,
+            );
+
+        let report = check_links(&output_fs, &"public", &HashMap::new()).unwrap();
+
+        assert_eq!(
+            report
+                .broken
+                .iter()
+                .map(|broken| broken.link.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/blog/%C3%BCber-alles/"],
+            "Percent encoded non-ASCII slug should be decoded!"
+        );
+    }
+}
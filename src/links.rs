@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::pages::Page;
+
+/// Prefix of a link to a content file relative to the site root, e.g. `@/blog/test-hello.md`.
+pub const ROOT_LINK_PREFIX: &str = "@/";
+
+/// Map of every content source file to the final URL of its page, used to rewrite links between
+/// markdown files into links of the rendered pages.
+#[derive(Debug, Default, Clone)]
+pub struct InternalLinks {
+    root_dir: PathBuf,
+    urls: HashMap<PathBuf, String>,
+}
+
+/// Lexically resolve `.` and `..` of `path`, without touching the filesystem.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Make the root relative `url` (starting with `/`) relative to the page rendered on
+/// `current_output_file` (relative to the output root), so it works when opened from disk.
+/// Directory URLs, either ending with `/` or with no extension on the last segment (e.g.
+/// `/blog`), will point to their `index.html`. Any other URL is returned as is.
+pub fn relative_to(url: &str, current_output_file: &Path) -> String {
+    if !url.starts_with('/') || url.starts_with("//") {
+        return url.to_owned();
+    }
+
+    let (url_path, suffix) = match url.find(['#', '?']) {
+        Some(suffix_start) => url.split_at(suffix_start),
+        None => (url, ""),
+    };
+
+    let mut target_segments: Vec<&str> = url_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if url_path.ends_with('/')
+        || target_segments
+            .last()
+            .is_some_and(|last_segment| !last_segment.contains('.'))
+    {
+        target_segments.push("index.html");
+    }
+
+    let current_dir_segments: Vec<String> = current_output_file
+        .parent()
+        .map(|current_dir| {
+            current_dir
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // The last target segment is the file itself, so it never counts as a shared directory
+    let shared_dirs = current_dir_segments
+        .iter()
+        .zip(&target_segments[..target_segments.len().saturating_sub(1)])
+        .take_while(|(current_segment, target_segment)| current_segment == target_segment)
+        .count();
+
+    let mut relative_url = "../".repeat(current_dir_segments.len() - shared_dirs);
+    relative_url.push_str(&target_segments[shared_dirs..].join("/"));
+    relative_url.push_str(suffix);
+
+    relative_url
+}
+
+/// Make every root relative URL of the `srcset` attribute value relative, keeping the width or
+/// density descriptor of each of them, see [`relative_to`].
+fn relative_srcset(srcset: &str, current_output_file: &Path) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let url_start = candidate.len() - candidate.trim_start().len();
+            let url_end = candidate[url_start..]
+                .find(char::is_whitespace)
+                .map_or(candidate.len(), |url_end| url_start + url_end);
+
+            format!(
+                "{}{}{}",
+                &candidate[..url_start],
+                relative_to(&candidate[url_start..url_end], current_output_file),
+                &candidate[url_end..]
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Rewrite every root relative URL on the `href`, `src` and `srcset` attributes of `html` to be
+/// relative to `current_output_file`, see [`relative_to`].
+pub fn rewrite_root_urls(html: &str, current_output_file: &Path) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut remaining = html;
+
+    while let Some(attribute_start) = remaining.find(['h', 's']) {
+        let (before, rest) = remaining.split_at(attribute_start);
+        output.push_str(before);
+
+        let is_attribute_boundary = before
+            .chars()
+            .last()
+            .or(output.chars().last())
+            .is_some_and(char::is_whitespace);
+        let attribute = ["href=", "src=", "srcset="]
+            .into_iter()
+            .find(|attribute| rest.starts_with(attribute));
+
+        let (Some(attribute), true) = (attribute, is_attribute_boundary) else {
+            output.push_str(&rest[..1]);
+            remaining = &rest[1..];
+            continue;
+        };
+
+        let value_part = &rest[attribute.len()..];
+        let Some(quote @ ('"' | '\'')) = value_part.chars().next() else {
+            output.push_str(attribute);
+            remaining = value_part;
+            continue;
+        };
+        let value_end = value_part[1..]
+            .find(quote)
+            .map(|value_end| value_end + 1)
+            .unwrap_or(value_part.len());
+
+        output.push_str(attribute);
+        output.push(quote);
+        let value = &value_part[1..value_end];
+        if attribute == "srcset=" {
+            output.push_str(&relative_srcset(value, current_output_file));
+        } else {
+            output.push_str(&relative_to(value, current_output_file));
+        }
+        remaining = &value_part[value_end..];
+    }
+
+    output.push_str(remaining);
+
+    output
+}
+
+impl InternalLinks {
+    /// This function assume that `root_page` is the root of all of the pages.
+    pub fn from_page(root_page: &Page) -> Self {
+        let mut internal_links = Self {
+            root_dir: normalize_path(
+                root_page
+                    .content
+                    .source_path
+                    .parent()
+                    .unwrap_or(Path::new("")),
+            ),
+            urls: HashMap::new(),
+        };
+
+        for page in root_page.all_pages() {
+            internal_links
+                .urls
+                .insert(normalize_path(&page.content.source_path), page.url());
+        }
+
+        internal_links
+    }
+
+    /// Resolve `link` written on the content file `source_path`. Returns `None` if it's not an
+    /// internal link (external URL, anchor only, or not a `.md` file), or the error message if
+    /// the linked content does not exist.
+    pub fn resolve(&self, source_path: &Path, link: &str) -> Option<Result<String, String>> {
+        let (link_path, fragment) = match link.find(['#', '?']) {
+            Some(fragment_start) => link.split_at(fragment_start),
+            None => (link, ""),
+        };
+
+        let target_path = if let Some(root_link) = link_path.strip_prefix(ROOT_LINK_PREFIX) {
+            self.root_dir.join(root_link)
+        } else if link_path.ends_with(".md")
+            && !link_path.starts_with('/')
+            && !link_path.contains("://")
+        {
+            source_path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(link_path)
+        } else {
+            return None;
+        };
+
+        match self.urls.get(&normalize_path(&target_path)) {
+            Some(url) => Some(Ok(format!("{url}{fragment}"))),
+            None => Some(Err(format!(
+                "Broken internal link '{link}' on {source_path:?}, there's no content on {target_path:?}!"
+            ))),
+        }
+    }
+
+    /// Rewrite every internal link of the markdown event stream of the content `source_path`.
+    pub fn rewrite_links<'a, I: Iterator<Item = Event<'a>>>(
+        &self,
+        events: I,
+        source_path: &Path,
+    ) -> Result<Vec<Event<'a>>, String> {
+        events
+            .map(|event| match event {
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => {
+                    let dest_url = match self.resolve(source_path, &dest_url) {
+                        Some(resolved_url) => resolved_url?.into(),
+                        None => dest_url,
+                    };
+
+                    Ok(Event::Start(Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }))
+                }
+                event => Ok(event),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod links_test {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::SiteConfig,
+        data::SiteData,
+        links::{relative_to, rewrite_root_urls, InternalLinks},
+        pages::Page,
+        vfs::DiskFs,
+    };
+
+    const MAIN_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+    fn get_test_links() -> (InternalLinks, PathBuf) {
+        let mut root_path = PathBuf::from(MAIN_DIR);
+        root_path.push("test_files");
+
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &root_path,
+            &SiteConfig::default(),
+            &SiteData::default(),
+        )
+        .unwrap();
+
+        (InternalLinks::from_page(&page_root), root_path)
+    }
+
+    #[test]
+    fn relative_and_root_links_resolved() {
+        let (links, root_path) = get_test_links();
+        let source_path = root_path.join("_index.md");
+
+        assert_eq!(
+            links.resolve(&source_path, "./blog/test-hello.md#how"),
+            Some(Ok("/blog/test-hello/#how".into())),
+            "Relative link should be resolved with the fragment!"
+        );
+        assert_eq!(
+            links.resolve(&root_path.join("blog/test-hello.md"), "@/blog/_index.md"),
+            Some(Ok("/blog/".into())),
+            "Root link should be resolved to the section URL!"
+        );
+        assert_eq!(
+            links.resolve(&source_path, "https://example.com/a.md"),
+            None,
+            "External link should not be touched!"
+        );
+        assert!(
+            links
+                .resolve(&source_path, "blog/missing.md")
+                .is_some_and(|resolved| resolved.is_err()),
+            "Missing content should be an error!"
+        );
+    }
+
+    #[test]
+    fn relative_urls() {
+        let current_file = PathBuf::from("blog/test-hello/index.html");
+
+        assert_eq!(relative_to("/", &current_file), "../../index.html");
+        assert_eq!(relative_to("/blog/", &current_file), "../index.html");
+        assert_eq!(
+            relative_to("/blog/test-hello/#how", &current_file),
+            "index.html#how"
+        );
+        assert_eq!(
+            relative_to("/highlight.css", &PathBuf::from("index.html")),
+            "highlight.css"
+        );
+        assert_eq!(
+            relative_to("https://example.com/", &current_file),
+            "https://example.com/"
+        );
+        assert_eq!(
+            relative_to("/blog", &current_file),
+            "../index.html",
+            "URL without extension should point to its index.html!"
+        );
+
+        assert_eq!(
+            rewrite_root_urls(
+                r#"<a class="x" href="/blog/">Blog</a><img src='/cat.png'> <p>src="/not-attribute"</p>"#,
+                &PathBuf::from("blog/index.html")
+            ),
+            r#"<a class="x" href="index.html">Blog</a><img src='../cat.png'> <p>src="/not-attribute"</p>"#
+        );
+        assert_eq!(
+            rewrite_root_urls(
+                r#"<img srcset="/cat-1x.png 1x, /cat-2x.png 2x,https://example.com/cat.png 3x">"#,
+                &PathBuf::from("blog/index.html")
+            ),
+            r#"<img srcset="../cat-1x.png 1x, ../cat-2x.png 2x,https://example.com/cat.png 3x">"#,
+            "Every URL of srcset should be rewritten!"
+        );
+    }
+}
@@ -1,17 +1,181 @@
 use core::panic;
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    time::Instant,
+};
 
-use simplistis::pages::Page;
+use log::{
+    kv::{Error, Key, Value, VisitSource},
+    LevelFilter, Log, Metadata, Record,
+};
+use serde_json::{json, Map};
+use simplistis::{
+    link_checker::{check_links, output_sources},
+    report::BuildReport,
+    site::Site,
+    vfs::DiskFs,
+};
+
+const USAGE: &str = "usage: simplistis [-q | -v | -vv] [--log-format text|json] [--report build-report.json] [build | check-links] [template directory root] [output directory root]";
+
+/// Collects the structured fields of a log record (e.g. `stage`, `file`, `page`).
+struct JsonFields(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        self.0.insert(key.to_string(), value.to_string().into());
+
+        Ok(())
+    }
+}
+
+/// Prints every diagnostic of the library to stderr, as text or as one JSON object per line.
+struct StderrLogger {
+    is_json: bool,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let mut stderr = io::stderr().lock();
+
+        let _ = if self.is_json {
+            let mut fields = JsonFields(Map::new());
+            let _ = record.key_values().visit(&mut fields);
+
+            let mut line = json!({
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            line.as_object_mut().unwrap().append(&mut fields.0);
+
+            writeln!(stderr, "{line}")
+        } else {
+            writeln!(stderr, "{:<5} {}", record.level(), record.args())
+        };
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Remove `flag` and its value from `args`, returns the value if the flag is given.
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    args.remove(index);
+
+    if index >= args.len() {
+        println!("{flag} needs a value, {USAGE}");
+        panic!()
+    }
+
+    Some(args.remove(index))
+}
+
+/// Remove the logging flags from `args`, and set up the logger with them.
+fn init_logger(args: &mut Vec<String>) {
+    let mut level = LevelFilter::Info;
+    let is_json = match take_flag_value(args, "--log-format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => {
+            println!("--log-format needs to be text or json, {USAGE}");
+            panic!()
+        }
+    };
+
+    let mut index = 1;
+    while index < args.len() {
+        match args[index].as_str() {
+            "-q" | "--quiet" => level = LevelFilter::Warn,
+            "-v" | "--verbose" => level = LevelFilter::Debug,
+            "-vv" => level = LevelFilter::Trace,
+            _ => {
+                index += 1;
+                continue;
+            }
+        }
+
+        args.remove(index);
+    }
+
+    log::set_boxed_logger(Box::new(StderrLogger { is_json })).unwrap();
+    log::set_max_level(level);
+}
+
+/// Human readable size e.g. `12.3 KiB`.
+fn format_size(size: u64) -> String {
+    match size {
+        0..=1023 => format!("{size} B"),
+        1024..=1048575 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / 1048576.0),
+    }
+}
+
+fn print_build_report(report: &BuildReport) {
+    let sections: Vec<String> = report
+        .sections
+        .iter()
+        .map(|(section, pages)| format!("{section} {pages}"))
+        .collect();
+    println!(
+        "[build] Rendered {} pages ({})",
+        report.sections.values().sum::<usize>(),
+        sections.join(", ")
+    );
+
+    for skipped in &report.skipped {
+        println!("[build] Skipped {:?}, {}", skipped.file, skipped.reason);
+    }
+
+    let stages: Vec<String> = report
+        .stages
+        .iter()
+        .map(|stage| format!("{} {:.1}ms", stage.stage, stage.duration_ms))
+        .collect();
+    println!("[build] Took {}", stages.join(", "));
+
+    let biggest_pages: Vec<String> = report
+        .biggest_pages
+        .iter()
+        .take(3)
+        .map(|page| format!("{:?} {}", page.file, format_size(page.size)))
+        .collect();
+    println!("[build] Biggest pages {}", biggest_pages.join(", "));
+
+    println!(
+        "[build] Wrote {} files, {} in total",
+        report.output_files,
+        format_size(report.total_size)
+    );
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    init_logger(&mut args);
+    let report_file = take_flag_value(&mut args, "--report");
+
+    // `build` is the default command, `check-links` builds the site as usual, then checks the
+    // links of the output
+    let is_check_links = args.get(1).is_some_and(|command| command == "check-links");
+    if is_check_links || args.get(1).is_some_and(|command| command == "build") {
+        args.remove(1);
+    }
 
     let Some(template_directory_raw) = args.get(1) else {
-        println!("No template directory root given, usage: simplistis [template directory root] [output directory root]");
+        println!("No template directory root given, {USAGE}");
         panic!()
     };
     let Some(output_directory_root) = args.get(2) else {
-        println!("No template directory root given, usage: simplistis [template directory root] [output directory root]");
+        println!("No output directory root given, {USAGE}");
         panic!()
     };
 
@@ -22,6 +186,55 @@ fn main() {
         fs::remove_dir_all(&output_dir).unwrap();
     }
 
-    let pages = Page::from_dir(&template_dir).unwrap();
-    pages.render_all(&output_dir).unwrap();
+    let site = Site::from_dir(&template_dir).unwrap();
+    let sources = output_sources(site.root_page());
+
+    let mut build_report = site.render_to_dir(&output_dir).unwrap();
+    let mut has_broken_links = false;
+
+    if is_check_links {
+        let check_start = Instant::now();
+        let report = check_links(&DiskFs, &output_dir, &sources).unwrap();
+        build_report.add_stage("check-links", check_start.elapsed());
+
+        for external in &report.external {
+            println!(
+                "[check-links] External link '{}' on {:?} (not checked)",
+                external.link, external.output_file
+            );
+        }
+
+        for broken in &report.broken {
+            let source_file = broken
+                .source_file
+                .as_ref()
+                .map(|source_file| format!("{source_file:?}"))
+                .unwrap_or("no source file".into());
+
+            println!(
+                "[check-links] Broken link '{}' on {:?} ({source_file}), {}",
+                broken.link, broken.output_file, broken.reason
+            );
+        }
+
+        println!(
+            "[check-links] Checked {} files, {} broken links, {} external links",
+            report.checked_files,
+            report.broken.len(),
+            report.external.len()
+        );
+
+        has_broken_links = !report.broken.is_empty();
+    }
+
+    print_build_report(&build_report);
+
+    if let Some(report_file) = report_file {
+        fs::write(&report_file, build_report.to_json().unwrap()).unwrap();
+        println!("[build] Report written to {report_file:?}");
+    }
+
+    if has_broken_links {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,245 @@
+use std::collections::{BTreeMap, HashMap};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SiteConfig, pages::Page};
+
+/// One entry of a named menu, set on `config.toml` as e.g. `[[menus.main]]`, or on the front
+/// matter of a content as e.g. `menu = { main = { weight = 10 } }`.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct MenuEntry {
+    /// Defaults to the title of the page on front matter, required on `config.toml`
+    pub name: Option<String>,
+    /// Always the URL of the page on front matter, required on `config.toml`
+    pub url: Option<String>,
+    /// Lowest weight first, entries without weight are the last
+    pub weight: Option<i64>,
+    /// Used by other entries as their `parent`, defaults to `name`
+    pub identifier: Option<String>,
+    /// The `identifier` of the entry this entry is nested under, on the same menu
+    pub parent: Option<String>,
+}
+
+/// One entry of a menu given to the templates, already nested and sorted.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct MenuItem {
+    pub identifier: String,
+    pub name: String,
+    pub url: String,
+    pub weight: Option<i64>,
+    /// True if `url` is the URL of the page being rendered
+    pub active: bool,
+    /// True if one of the `children`, at any depth, is active
+    pub child_active: bool,
+    pub children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    /// Set the active flags of `items` and their children, returns true if any of them is active.
+    fn mark_active(items: &mut [Self], current_url: &str) -> bool {
+        let mut any_active = false;
+
+        for item in items {
+            item.child_active = Self::mark_active(&mut item.children, current_url);
+            item.active = item.url == current_url;
+            any_active |= item.active || item.child_active;
+        }
+
+        any_active
+    }
+}
+
+/// Every named menu of the site, collected from `config.toml` and the front matter of the pages.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Menus {
+    menus: BTreeMap<String, Vec<MenuItem>>,
+}
+
+impl Menus {
+    /// Nest the `entries` of one menu under their parent, each level sorted by weight then name.
+    fn build_menu(menu_name: &str, mut entries: Vec<(Option<String>, MenuItem)>) -> Vec<MenuItem> {
+        entries.sort_by(|(_, first), (_, second)| {
+            (first.weight.is_none(), first.weight, &first.name).cmp(&(
+                second.weight.is_none(),
+                second.weight,
+                &second.name,
+            ))
+        });
+
+        let mut children = HashMap::<String, Vec<MenuItem>>::new();
+        let mut top_level = Vec::new();
+
+        for (parent, item) in entries {
+            match parent {
+                Some(parent) => children.entry(parent).or_default().push(item),
+                None => top_level.push(item),
+            }
+        }
+
+        fn attach_children(items: &mut [MenuItem], children: &mut HashMap<String, Vec<MenuItem>>) {
+            for item in items {
+                if let Some(mut item_children) = children.remove(&item.identifier) {
+                    attach_children(&mut item_children, children);
+                    item.children = item_children;
+                }
+            }
+        }
+
+        attach_children(&mut top_level, &mut children);
+
+        for (parent, orphans) in children {
+            for orphan in orphans {
+                warn!(
+                    stage = "render";
+                    "[Menus::build_menu] Parent '{parent}' of '{}' on menu '{menu_name}' not found, the entry is skipped!",
+                    orphan.name
+                );
+            }
+        }
+
+        top_level
+    }
+
+    /// Collect the menus from `menus` of `config` and the `menu` front matter of every page on
+    /// the tree of `root_page`.
+    pub fn from_page(root_page: &Page, config: &SiteConfig) -> Self {
+        let mut entries = BTreeMap::<String, Vec<(Option<String>, MenuItem)>>::new();
+
+        for (menu_name, menu_entries) in &config.menus {
+            for entry in menu_entries {
+                let (Some(name), Some(url)) = (&entry.name, &entry.url) else {
+                    warn!(
+                        stage = "render", file = SiteConfig::FILE_NAME;
+                        "[Menus::from_page] Entry of menu '{menu_name}' on {} needs both name and url!",
+                        SiteConfig::FILE_NAME
+                    );
+                    continue;
+                };
+
+                entries.entry(menu_name.clone()).or_default().push((
+                    entry.parent.clone(),
+                    MenuItem {
+                        identifier: entry.identifier.clone().unwrap_or_else(|| name.clone()),
+                        name: name.clone(),
+                        url: url.clone(),
+                        weight: entry.weight,
+                        active: false,
+                        child_active: false,
+                        children: vec![],
+                    },
+                ));
+            }
+        }
+
+        for page in root_page.all_pages() {
+            let metadata = &page.content.metadata;
+
+            for (menu_name, entry) in metadata.menu.iter().flatten() {
+                let name = entry
+                    .name
+                    .clone()
+                    .or_else(|| metadata.title.clone())
+                    .unwrap_or_else(|| page.path.clone());
+
+                entries.entry(menu_name.clone()).or_default().push((
+                    entry.parent.clone(),
+                    MenuItem {
+                        identifier: entry.identifier.clone().unwrap_or_else(|| name.clone()),
+                        name,
+                        url: page.url(),
+                        weight: entry.weight.or(metadata.weight),
+                        active: false,
+                        child_active: false,
+                        children: vec![],
+                    },
+                ));
+            }
+        }
+
+        Self {
+            menus: entries
+                .into_iter()
+                .map(|(menu_name, menu_entries)| {
+                    let menu = Self::build_menu(&menu_name, menu_entries);
+                    (menu_name, menu)
+                })
+                .collect(),
+        }
+    }
+
+    /// Every menu with the `active` flags set for the page at `current_url`.
+    pub fn for_url(&self, current_url: &str) -> BTreeMap<String, Vec<MenuItem>> {
+        let mut menus = self.menus.clone();
+
+        for menu in menus.values_mut() {
+            MenuItem::mark_active(menu, current_url);
+        }
+
+        menus
+    }
+}
+
+#[cfg(test)]
+mod menu_test {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::SiteConfig,
+        data::SiteData,
+        menu::{MenuEntry, Menus},
+        pages::Page,
+        vfs::DiskFs,
+    };
+
+    const MAIN_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+    #[test]
+    fn menus_collected() {
+        let mut root_path = PathBuf::from(MAIN_DIR);
+        root_path.push("test_files");
+
+        let mut config = SiteConfig::default();
+        config.menus.insert(
+            "main".into(),
+            vec![
+                MenuEntry {
+                    name: Some("Source".into()),
+                    url: Some("https://github.com/fauh45/simplistis".into()),
+                    weight: Some(20),
+                    ..Default::default()
+                },
+                MenuEntry {
+                    name: Some("Hello".into()),
+                    url: Some("/blog/test-hello/".into()),
+                    parent: Some("Blog".into()),
+                    ..Default::default()
+                },
+            ],
+        );
+
+        let page_root = Page::from_dir(&DiskFs, &root_path, &config, &SiteData::default()).unwrap();
+        let menus = Menus::from_page(&page_root, &config).for_url("/blog/test-hello/");
+        let main_menu = &menus["main"];
+
+        assert_eq!(
+            main_menu
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Blog", "Source"],
+            "Menu should be sorted by weight!"
+        );
+        assert_eq!(main_menu[0].url, "/blog/");
+        assert!(!main_menu[0].active, "Parent should not be active!");
+        assert!(
+            main_menu[0].child_active,
+            "Parent should have active child!"
+        );
+        assert!(
+            main_menu[0].children[0].active,
+            "Current page should be active!"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{pages::Page, preparser::ContentMetadata};
+
+/// How the pages of a directory are ordered, set on the `_index.md` front matter as `sort_by`.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Newest `updated_at` first, pages without `updated_at` are the last
+    #[default]
+    Date,
+    Title,
+    /// Lowest `weight` first, pages without weight are the last
+    Weight,
+}
+
+impl SortBy {
+    /// Sort `pages` in place, pages that are equal keep their path order.
+    pub fn sort_pages(&self, pages: &mut [Page]) {
+        pages.sort_by(|first, second| first.path.cmp(&second.path));
+
+        match self {
+            SortBy::Date => pages.sort_by_key(|page| {
+                // Unset `updated_at` is the parse time, so it's ignored to keep the path order
+                let metadata = &page.content.metadata;
+                (
+                    !metadata.is_date_set,
+                    metadata.is_date_set.then_some(Reverse(metadata.updated_at)),
+                )
+            }),
+            SortBy::Title => pages.sort_by_cached_key(|page| {
+                page.content
+                    .metadata
+                    .title
+                    .as_ref()
+                    .map(|title| title.to_lowercase())
+            }),
+            SortBy::Weight => pages.sort_by_key(|page| {
+                // `false` is less than `true`, so pages with weight are put first
+                (
+                    page.content.metadata.weight.is_none(),
+                    page.content.metadata.weight,
+                )
+            }),
+        }
+    }
+}
+
+/// The data of another page given to the templates, the content metadata fields are flattened
+/// so it can be used as `{{page.title}}` or `{{page.url}}`.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct PageRef {
+    pub url: String,
+    pub path: String,
+    pub is_dir_root: bool,
+    #[serde(flatten)]
+    pub metadata: ContentMetadata,
+}
+
+impl From<&Page> for PageRef {
+    fn from(page: &Page) -> Self {
+        Self {
+            url: page.url(),
+            path: page.path.clone(),
+            is_dir_root: page.is_dir_root,
+            metadata: page.content.metadata.clone(),
+        }
+    }
+}
+
+/// A directory of the site with its pages and sub-directories, in their sort order.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct SectionTree {
+    #[serde(flatten)]
+    pub section: PageRef,
+    pub pages: Vec<PageRef>,
+    pub sections: Vec<SectionTree>,
+}
+
+impl From<&Page> for SectionTree {
+    fn from(page: &Page) -> Self {
+        Self {
+            section: PageRef::from(page),
+            pages: page
+                .child
+                .iter()
+                .filter(|child| !child.is_dir_root)
+                .map(PageRef::from)
+                .collect(),
+            sections: page
+                .child
+                .iter()
+                .filter(|child| child.is_dir_root)
+                .map(SectionTree::from)
+                .collect(),
+        }
+    }
+}
+
+/// Where a page is on the site tree, given to its template.
+#[derive(Debug, Default, Serialize, PartialEq, Clone)]
+pub struct PageNavigation {
+    /// The directory of the page, for a directory root this is its parent directory
+    pub section: Option<PageRef>,
+    /// From the root page to `section`, for breadcrumbs
+    pub ancestors: Vec<PageRef>,
+    /// The page before this one on the sort order of its directory, pages and directories are
+    /// ordered separately
+    pub prev: Option<PageRef>,
+    pub next: Option<PageRef>,
+}
+
+#[cfg(test)]
+mod navigation_test {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::SiteConfig, content::Content, data::SiteData, navigation::SectionTree, pages::Page,
+        vfs::DiskFs,
+    };
+
+    const MAIN_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+    #[test]
+    fn section_tree_built() {
+        let mut root_path = PathBuf::from(MAIN_DIR);
+        root_path.push("test_files");
+
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &root_path,
+            &SiteConfig::default(),
+            &SiteData::default(),
+        )
+        .unwrap();
+        let site_tree = SectionTree::from(&page_root);
+
+        assert_eq!(site_tree.section.url, "/");
+        assert!(
+            site_tree.pages.is_empty(),
+            "Root directory should not have pages!"
+        );
+        assert_eq!(site_tree.sections.len(), 1);
+        assert_eq!(site_tree.sections[0].pages[0].url, "/blog/test-hello/");
+
+        let site_json = serde_json::to_value(&site_tree).unwrap();
+
+        assert_eq!(
+            site_json["sections"][0]["title"], "Blog Directory",
+            "Metadata should be flattened!"
+        );
+    }
+
+    #[test]
+    fn undated_pages_sorted_last() {
+        let config = SiteConfig::default();
+        let mut blog_page = Page::new_dir(
+            "blog",
+            Content::from_markdown("Blog".into(), PathBuf::from("blog/_index.md")),
+            String::new(),
+            &config,
+        );
+        for (file_name, markdown) in [
+            ("a.md", "A"),
+            ("c.md", "---\nupdated_at = 2024-01-01T00:00:00Z\n---\nC"),
+            ("b.md", "B"),
+            ("d.md", "---\nupdated_at = 2024-05-03T00:00:00Z\n---\nD"),
+        ] {
+            blog_page.add_content(
+                Content::from_markdown(markdown.into(), PathBuf::from("blog").join(file_name)),
+                "",
+                &config,
+            );
+        }
+
+        blog_page.sort_child();
+
+        assert_eq!(
+            blog_page
+                .child
+                .iter()
+                .map(|page| page.path())
+                .collect::<Vec<_>>(),
+            vec!["blog/d", "blog/c", "blog/a", "blog/b"],
+            "Undated pages should be after the dated ones on their path order!"
+        );
+    }
+}
@@ -1,85 +1,156 @@
 use std::{
-    fs::{self, File},
-    io::Read,
+    collections::BTreeMap,
     path::{Path, PathBuf},
 };
 
-use handlebars::{to_json, Handlebars};
+use handlebars::to_json;
+use log::{debug, warn};
 use serde::Serialize;
 use serde_json::{value::Value, Map};
 
-use crate::content::Content;
+use crate::{
+    config::SiteConfig,
+    content::Content,
+    data::SiteData,
+    highlight::HighlightConfig,
+    i18n::{translation_key, Translations},
+    links::{normalize_path, rewrite_root_urls, InternalLinks},
+    menu::Menus,
+    navigation::{PageNavigation, PageRef, SectionTree},
+    permalink::{expand_permalink, join_page_path},
+    redirect::{collect_redirects, render_redirects},
+    renderer::Renderer,
+    report::SkippedFile,
+    sitemap::render_sitemaps,
+    vfs::{OutputSink, SourceFs},
+};
+
+/// Two or more sources that would be rendered to the same output file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OutputCollision {
+    /// Relative to the output root
+    pub output_file: PathBuf,
+    pub sources: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for OutputCollision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} is produced by", self.output_file)?;
+
+        for source in &self.sources {
+            write!(f, " {source:?}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write every output of [`Page::render_to_memory`] to `output_sink`.
+pub(crate) fn write_outputs(
+    outputs: &BTreeMap<PathBuf, Vec<u8>>,
+    output_sink: &mut dyn OutputSink,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (output_file, output) in outputs {
+        debug!(stage = "write", file:% = output_file.display(); "[pages::write_outputs] Writing file {output_file:?}");
+        output_sink.write(output_file, output)?;
+    }
+
+    debug!(stage = "write"; "[pages::write_outputs] Wrote {} files", outputs.len());
+
+    Ok(())
+}
 
 #[derive(Debug, Serialize, Clone)]
 pub struct Page {
     pub(crate) path: String,
-    content: Content,
+    pub(crate) content: Content,
     #[serde(skip_serializing)]
     template: String,
 
     /// If true this represent the page to be the root of a directory
     pub(crate) is_dir_root: bool,
 
+    /// If true this page is rendered to `<path>.html` instead of `<path>/index.html`
+    #[serde(skip_serializing)]
+    pub(crate) is_ugly_url: bool,
+
+    /// If true this page is a special page (e.g. `404.md`), always rendered to `<path>.html` at
+    /// the output root, and never listed with the other pages
+    #[serde(skip_serializing)]
+    pub(crate) is_special: bool,
+
     #[serde(skip_serializing)]
     pub(crate) child: Vec<Page>,
+
+    /// Only filled on the root page
+    #[serde(skip_serializing)]
+    pub(crate) special_pages: Vec<Page>,
+
+    /// The root pages of every other language, only filled on the root page loaded by
+    /// [`Page::from_dir`]
+    #[serde(skip_serializing)]
+    pub(crate) language_roots: Vec<Page>,
+
+    /// Sources that are not loaded, only filled on the root page loaded by [`Page::from_dir`]
+    #[serde(skip_serializing)]
+    pub(crate) skipped_files: Vec<SkippedFile>,
 }
 
 impl Page {
-    fn get_template_content<P: AsRef<Path>>(path: &P, path_file_name: &str) -> Option<String> {
+    fn get_template_content<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        path: &P,
+        path_file_name: &str,
+    ) -> Option<String> {
         let mut template_path = PathBuf::new();
         template_path.push(path.as_ref());
         template_path.push(path_file_name);
 
-        if !template_path.exists() {
+        if !source_fs.is_file(&template_path) {
             return None;
         }
 
-        let mut template_content = String::new();
-
-        // Safe to unwrap as we check if path exist or not, though there could be problem
-        // where the file is not allowed to be open by the user
-        // TODO: Handle file open error
-        if let Err(err) = (File::open(template_path))
-            .unwrap()
-            .read_to_string(&mut template_content)
-        {
-            println!("[Page::parse_one_page] Cannot open {path_file_name} file! Error: {err:#?}");
+        match source_fs.read_to_string(&template_path) {
+            Ok(template_content) => Some(template_content),
+            Err(err) => {
+                warn!(stage = "load", file:% = template_path.display(); "[Page::parse_one_page] Cannot open {template_path:?} file! Error: {err}");
 
-            return None;
+                None
+            }
         }
-
-        Some(template_content)
     }
-    /// This function assume `path` is the root of a page.
-    fn parse_one_page<P: AsRef<Path>, BP: AsRef<Path>>(base_path: &BP, path: &P) -> Option<Self> {
+
+    /// This function assume `path` is the root of a page. Returns the page of the default
+    /// language, and the page of every other language with its own index, either
+    /// `_index.<lang>.md` on the directory or `_index.md` on the language subtree e.g.
+    /// `id/blog/_index.md`.
+    fn parse_one_page<P: AsRef<Path>, BP: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        base_path: &BP,
+        path: &P,
+        config: &SiteConfig,
+        skipped_files: &mut Vec<SkippedFile>,
+    ) -> Option<(Self, Vec<Self>)> {
         let mut current_path = PathBuf::new();
         current_path.push(path.as_ref());
 
-        let Some(current_dir) = current_path.file_name() else {
-            println!("[Page::parse_one_page] Cannot get dir name!");
-
-            return None;
-        };
-
         let mut index_content_path = current_path.clone();
         index_content_path.push("_index.md");
 
-        if !index_content_path.exists() {
-            println!("[Page::parse_one_page] No _index.md on directory {current_path:?}!");
+        if !source_fs.is_file(&index_content_path) {
+            warn!(stage = "load", file:% = current_path.display(); "[Page::parse_one_page] No _index.md on directory {current_path:?}!");
+            skipped_files.push(SkippedFile::new(&current_path, "no _index.md"));
 
             return None;
         }
 
-        let template_content = Self::get_template_content(path, "template.hbs")?;
-        let content_template_content = Self::get_template_content(path, "content.hbs");
-
-        let Ok(index_content) = Content::from_file(&index_content_path) else {
-            println!(
-                "[Page::parse_one_page] Failed to parse _index.md on directory {current_path:?}!"
-            );
+        let Some(template_content) = Self::get_template_content(source_fs, path, "template.hbs")
+        else {
+            skipped_files.push(SkippedFile::new(&current_path, "no template.hbs"));
 
             return None;
         };
+        let content_template_content = Self::get_template_content(source_fs, path, "content.hbs");
 
         let mut current_root_path = String::from("");
         current_root_path.push_str(
@@ -90,36 +161,426 @@ impl Page {
                 .unwrap(),
         );
 
-        let mut current_root = Self {
-            path: current_root_path.clone(),
+        let default_language = config.default_language();
+        let mut current_root = Self::parse_index(
+            source_fs,
+            &index_content_path,
+            &current_root_path,
+            &template_content,
+            default_language,
+            config,
+            skipped_files,
+        )?;
+
+        let mut contents = Content::from_dir_or_skip(source_fs, &current_path, skipped_files);
+        // Index of another language e.g. `_index.id.md` is parsed with its language below, while
+        // `_index.fr.md` without `fr` on `languages` is an ordinary content
+        contents.retain(|content| {
+            config
+                .content_language(&content.source_path)
+                .is_none_or(|lang| {
+                    content.source_path.file_stem() != Some(format!("_index.{lang}").as_ref())
+                })
+        });
+        if current_root_path.is_empty() {
+            contents
+                .retain(|content| !Self::is_special_page_source(source_fs, &content.source_path));
+        }
+        // Contents of the language subtrees, e.g. `id/blog/post.md` is `blog/post.md` in `id`
+        for lang in config.other_languages() {
+            let subtree_path = base_path.as_ref().join(lang).join(&current_root_path);
+            if !source_fs.is_dir(&subtree_path) {
+                continue;
+            }
+
+            for mut content in Content::from_dir_or_skip(source_fs, &subtree_path, skipped_files) {
+                content.metadata.translation_key = content
+                    .source_path
+                    .file_name()
+                    .map(|file_name| normalize_path(&current_path.join(file_name)));
+                content.set_language(lang);
+
+                contents.push(content);
+            }
+        }
+        // Pattern on the `_index.md` front matter takes precedence over the site config
+        let permalink = current_root
+            .content
+            .metadata
+            .permalink
+            .clone()
+            .or_else(|| config.get_permalink(&current_root_path).cloned());
+
+        if !contents.is_empty() && content_template_content.is_none() {
+            warn!(stage = "load", file:% = current_path.display(); "[Page::parse_one_page] There's no content template (content.hbs) but there's content, skipping parsing content on directory {current_path:?}!");
+            for content in contents.drain(..) {
+                skipped_files.push(SkippedFile::new(
+                    &content.source_path,
+                    "no content.hbs on its directory",
+                ));
+            }
+        }
+        // Only used if there's content, which means there's a content template
+        let content_template_content = content_template_content.unwrap_or_default();
+
+        let mut contents_by_language = BTreeMap::<String, Vec<Content>>::new();
+        for mut content in contents {
+            let lang = match &content.metadata.lang {
+                Some(lang) => lang.clone(),
+                None => {
+                    let lang = config
+                        .content_language(&content.source_path)
+                        .unwrap_or(default_language)
+                        .to_owned();
+                    content.metadata.translation_key =
+                        Some(translation_key(&content.source_path, config));
+                    content.set_language(&lang);
+
+                    lang
+                }
+            };
+
+            contents_by_language.entry(lang).or_default().push(content);
+        }
+
+        for content in contents_by_language
+            .remove(default_language)
+            .unwrap_or_default()
+        {
+            current_root.push_content(
+                content,
+                &content_template_content,
+                permalink.as_deref(),
+                config,
+            );
+        }
+        current_root.sort_child();
+
+        let mut translated_roots = Vec::new();
+        for lang in config.other_languages() {
+            let contents = contents_by_language.remove(lang).unwrap_or_default();
+            let mut translated_index_path = current_path.join(format!("_index.{lang}.md"));
+            if !source_fs.is_file(&translated_index_path) {
+                translated_index_path = base_path
+                    .as_ref()
+                    .join(lang)
+                    .join(&current_root_path)
+                    .join("_index.md");
+            }
+
+            // A directory is only translated if it has its own index
+            if !source_fs.is_file(&translated_index_path) {
+                for content in contents {
+                    skipped_files.push(SkippedFile::new(
+                        &content.source_path,
+                        format!("no _index.{lang}.md on its directory"),
+                    ));
+                }
+
+                continue;
+            }
+
+            let Some(mut translated_root) = Self::parse_index(
+                source_fs,
+                &translated_index_path,
+                &join_page_path([config.language_prefix(lang).as_str(), &current_root_path]),
+                &template_content,
+                lang,
+                config,
+                skipped_files,
+            ) else {
+                continue;
+            };
+            translated_root.content.metadata.translation_key =
+                Some(normalize_path(&index_content_path));
+
+            let translated_permalink = translated_root
+                .content
+                .metadata
+                .permalink
+                .clone()
+                .or_else(|| permalink.clone());
+            for content in contents {
+                translated_root.push_content(
+                    content,
+                    &content_template_content,
+                    translated_permalink.as_deref(),
+                    config,
+                );
+            }
+            translated_root.sort_child();
+
+            translated_roots.push(translated_root);
+        }
+
+        Some((current_root, translated_roots))
+    }
+
+    /// Parse the index content of a directory e.g. `_index.md` or `_index.id.md` as the
+    /// directory page of `lang`.
+    fn parse_index(
+        source_fs: &dyn SourceFs,
+        index_content_path: &Path,
+        page_path: &str,
+        template: &str,
+        lang: &str,
+        config: &SiteConfig,
+        skipped_files: &mut Vec<SkippedFile>,
+    ) -> Option<Self> {
+        let mut index_content = match Content::from_file(source_fs, &index_content_path) {
+            Ok(index_content) => index_content,
+            Err(err) => {
+                warn!(
+                    stage = "load", file:% = index_content_path.display();
+                    "[Page::parse_index] Failed to parse {index_content_path:?}! Error: {err}"
+                );
+                skipped_files.push(SkippedFile::new(
+                    &index_content_path,
+                    format!("cannot parse, {err}"),
+                ));
+
+                return None;
+            }
+        };
+        index_content.set_language(lang);
+
+        Some(Self {
+            path: page_path.to_owned(),
             content: index_content,
-            template: template_content.clone(),
+            template: template.to_owned(),
             is_dir_root: true,
+            is_ugly_url: config.ugly_urls,
+            is_special: false,
             child: vec![],
-        };
+            special_pages: vec![],
+            language_roots: vec![],
+            skipped_files: vec![],
+        })
+    }
 
-        let contents = Content::from_dir(&current_path);
+    /// Add `content` as a child of this directory page, its path is the path override, or the
+    /// `permalink` pattern, or the slug on this directory. Pages of a language other than the
+    /// default are always put under the language prefix.
+    fn push_content(
+        &mut self,
+        mut content: Content,
+        template: &str,
+        permalink: Option<&str>,
+        config: &SiteConfig,
+    ) {
+        content.metadata.normalize_slug(&config.slugify);
 
-        if !contents.is_empty() && content_template_content.is_none() {
-            println!("[Page::parse_one_page] There's no content template (content.hbs) but there's content, skipping parsing content on directory {current_dir:?}!");
+        let permalink_path = permalink.and_then(|pattern| {
+            let expanded_path =
+                expand_permalink(pattern, &self.path, &content.metadata, &config.slugify);
+            if expanded_path.is_none() {
+                warn!(stage = "load", file:% = content.source_path.display(); "[Page::push_content] No updated_at for the dates of permalink '{pattern}', using the slug instead");
+            }
+
+            expanded_path
+        });
+
+        let mut child_path = if let Some(path_override) = &content.metadata.path {
+            join_page_path([path_override.as_str()])
+        } else if let Some(permalink_path) = permalink_path {
+            permalink_path
         } else {
-            for content in contents {
-                let mut child_path = current_root_path.clone();
-                child_path.push('/');
-                // This should be safe as `slug` is guaranteed to always be there
-                child_path.push_str(content.metadata.slug.as_ref().unwrap());
-
-                current_root.child.push(Self {
-                    path: child_path,
-                    template: content_template_content.as_ref().unwrap().to_string(),
-                    content,
-                    is_dir_root: false,
-                    child: vec![],
-                })
+            // This should be safe as `slug` is guaranteed to always be there
+            join_page_path([self.path.as_str(), content.metadata.slug.as_ref().unwrap()])
+        };
+
+        // Path overrides and permalink patterns are written without the language prefix
+        let language_prefix = content
+            .metadata
+            .lang
+            .as_deref()
+            .map(|lang| config.language_prefix(lang))
+            .unwrap_or_default();
+        if !language_prefix.is_empty()
+            && child_path != language_prefix
+            && !child_path.starts_with(&format!("{language_prefix}/"))
+        {
+            child_path = join_page_path([language_prefix.as_str(), child_path.as_str()]);
+        }
+
+        self.child.push(Self {
+            path: child_path,
+            template: template.to_owned(),
+            content,
+            is_dir_root: false,
+            is_ugly_url: config.ugly_urls,
+            is_special: false,
+            child: vec![],
+            special_pages: vec![],
+            language_roots: vec![],
+            skipped_files: vec![],
+        })
+    }
+
+    /// Add every translated directory to the root page of its language, this function assume
+    /// that `self` is the root page.
+    fn add_translated_dirs(
+        &mut self,
+        translated_pages: Vec<Self>,
+        skipped_files: &mut Vec<SkippedFile>,
+    ) {
+        for translated_page in translated_pages {
+            let lang = translated_page.content.metadata.lang.clone();
+
+            match self
+                .language_roots
+                .iter_mut()
+                .find(|language_root| language_root.content.metadata.lang == lang)
+            {
+                Some(language_root) => language_root.child.push(translated_page),
+                None => skipped_files.push(SkippedFile::new(
+                    &translated_page.content.source_path,
+                    format!("no _index.{}.md on the root", lang.unwrap_or_default()),
+                )),
+            }
+        }
+    }
+
+    /// Add the pages generated from `site_data` to their directory, see
+    /// [`crate::data::DataPages`]. This function assume that `self` is the root page, and
+    /// `root_path` is the root of all of the pages.
+    fn add_data_pages<P: AsRef<Path>>(
+        &mut self,
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+        config: &SiteConfig,
+        site_data: &SiteData,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if config.data_pages.is_empty() {
+            return Ok(());
+        }
+
+        for data_pages in &config.data_pages {
+            let Some(Value::Array(records)) = site_data.get(&data_pages.data) else {
+                return Err(
+                    format!("Data '{}' is not an array of records!", data_pages.data).into(),
+                );
+            };
+
+            let template_path = root_path.as_ref().join(&data_pages.template);
+            let template = source_fs
+                .read_to_string(&template_path)
+                .map_err(|err| format!("Cannot open template {template_path:?}! {err}"))?;
+
+            let section_path = join_page_path([data_pages.section.as_str()]);
+            let section = if section_path.is_empty() {
+                &mut *self
+            } else {
+                self.child
+                    .iter_mut()
+                    .find(|child| child.is_dir_root && child.path == section_path)
+                    .ok_or_else(|| {
+                        format!(
+                            "Directory '{section_path}' of data '{}' does not exist!",
+                            data_pages.data
+                        )
+                    })?
+            };
+
+            let permalink = section
+                .content
+                .metadata
+                .permalink
+                .clone()
+                .or_else(|| config.get_permalink(&section_path).cloned());
+
+            for (index, record) in records.iter().enumerate() {
+                let source_path = root_path
+                    .as_ref()
+                    .join(SiteData::DIR_NAME)
+                    .join(format!("{}[{index}]", data_pages.data));
+                let content = Content::from_data_record(record, source_path, &index.to_string())?;
+
+                debug!(
+                    stage = "load", file:% = content.source_path.display();
+                    "[Page::add_data_pages] Adding page '{}' from data '{}'",
+                    content.metadata.title.as_deref().unwrap_or_default(),
+                    data_pages.data
+                );
+                section.push_content(content, &template, permalink.as_deref(), config);
             }
+
+            section.sort_child();
+        }
+
+        Ok(())
+    }
+
+    /// A markdown file on the root directory with a template of the same name, e.g. `404.md` and
+    /// `404.hbs`, is a special page.
+    fn is_special_page_source(source_fs: &dyn SourceFs, source_path: &Path) -> bool {
+        let is_reserved_name = source_path
+            .file_stem()
+            .is_some_and(|file_stem| file_stem == "template" || file_stem == "content");
+
+        !is_reserved_name && source_fs.is_file(&source_path.with_extension("hbs"))
+    }
+
+    /// This function assume `root_path` is the root of all of the pages.
+    fn parse_special_pages<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+        config: &SiteConfig,
+        skipped_files: &mut Vec<SkippedFile>,
+    ) -> Vec<Self> {
+        let mut special_pages = Vec::new();
+
+        for content in Content::from_dir(source_fs, root_path) {
+            if !Self::is_special_page_source(source_fs, &content.source_path) {
+                continue;
+            }
+
+            let template_path = content.source_path.with_extension("hbs");
+            let Ok(template) = source_fs.read_to_string(&template_path) else {
+                warn!(stage = "load", file:% = template_path.display(); "[Page::parse_special_pages] Cannot open {template_path:?} file!");
+                skipped_files.push(SkippedFile::new(
+                    &content.source_path,
+                    format!("cannot open {template_path:?}"),
+                ));
+
+                continue;
+            };
+
+            // Safe to unwrap as the content is parsed from the file
+            let name = content.source_path.file_stem().unwrap().to_string_lossy();
+            debug!(stage = "load", file:% = content.source_path.display(); "[Page::parse_special_pages] Found special page '{name}'");
+
+            special_pages.push(Self {
+                path: name.into_owned(),
+                content,
+                template,
+                is_dir_root: false,
+                is_ugly_url: config.ugly_urls,
+                is_special: true,
+                child: vec![],
+                special_pages: vec![],
+                language_roots: vec![],
+                skipped_files: vec![],
+            });
+        }
+
+        special_pages
+    }
+
+    /// Every page on the tree of this page, including itself, the special pages and the pages of
+    /// every other language.
+    pub fn all_pages(&self) -> Vec<&Self> {
+        let mut all_pages = Vec::new();
+        let mut pages = vec![self];
+
+        while let Some(page) = pages.pop() {
+            all_pages.push(page);
+            pages.extend(page.child.iter());
+            pages.extend(page.special_pages.iter());
+            pages.extend(page.language_roots.iter());
         }
 
-        Some(current_root)
+        all_pages
     }
 
     /// This function assume that `path` is the root of all of the pages.
@@ -129,91 +590,514 @@ impl Page {
     /// (root:dir) ->
     /// ....(path:dir) ->
     /// ........(contents:file).md
+    /// ........(contents:file).(lang).md (optional, translated content:file)
     /// ........template.hbs (optional, path level template:file)
     /// ...._index.md (root page "/":file)
+    /// ...._index.(lang).md (optional, root page of another language:file)
+    /// ....(lang:dir) (optional, language subtree mirroring the root, e.g. `id/blog/post.md`)
     /// ....templates.hbs (root level template:file)
+    /// ....(special:file).md (optional, special page, e.g. 404.md)
+    /// ....(special:file).hbs (optional, special page template:file)
     /// ```
-    pub fn from_dir<P: AsRef<Path>>(root_path: &P) -> Option<Self> {
+    ///
+    /// `site_data` is the data already loaded from `_data/`, see [`SiteData::from_dir`], used for
+    /// the pages generated from data files.
+    pub fn from_dir<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+        config: &SiteConfig,
+        site_data: &SiteData,
+    ) -> Option<Self> {
         let root_base_path = Path::new(root_path.as_ref());
 
         let mut index_dir_path = PathBuf::new();
         index_dir_path.push(root_path);
 
-        let Some(mut root_page) = Self::parse_one_page(&root_base_path, &index_dir_path) else {
-            println!("[Page::from_dir] Failed parsing root directory!");
+        let mut skipped_files = Vec::new();
+        let Some((mut root_page, language_roots)) = Self::parse_one_page(
+            source_fs,
+            &root_base_path,
+            &index_dir_path,
+            config,
+            &mut skipped_files,
+        ) else {
+            warn!(stage = "load", file:% = root_base_path.display(); "[Page::from_dir] Failed parsing root directory!");
 
             return None;
         };
+        root_page.language_roots = language_roots;
+
+        if let Ok(entries) = source_fs.read_dir(root_path.as_ref()) {
+            for entry in entries {
+                let entry_path = entry.path;
 
-        if let Ok(entries) = fs::read_dir(root_path) {
-            for entry in entries.flatten() {
-                let entry_path = entry.path();
+                // Directories starting with `_` are reserved for simplistis, e.g. `_shortcodes`,
+                // and language subtrees are loaded with the directories they translate
+                if entry_path
+                    .file_name()
+                    .and_then(|dir_name| dir_name.to_str())
+                    .is_some_and(|dir_name| {
+                        dir_name.starts_with('_')
+                            || config.other_languages().any(|lang| lang == dir_name)
+                    })
+                {
+                    continue;
+                }
 
-                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
-                    let current_page = Self::parse_one_page(&root_base_path, &entry_path);
+                if entry.is_dir {
+                    let current_page = Self::parse_one_page(
+                        source_fs,
+                        &root_base_path,
+                        &entry_path,
+                        config,
+                        &mut skipped_files,
+                    );
 
-                    if let Some(current_page) = current_page {
+                    if let Some((current_page, translated_pages)) = current_page {
                         root_page.child.push(current_page);
+                        root_page.add_translated_dirs(translated_pages, &mut skipped_files);
                     } else {
-                        println!("[Page::from_dir] Cannot parse page: {entry:?}");
+                        warn!(stage = "load", file:% = entry_path.display(); "[Page::from_dir] Cannot parse page: {entry_path:?}");
                     }
                 }
             }
         } else {
-            println!("[Page::from_dir] Error reading root websiter path!")
+            warn!(stage = "load", file:% = root_base_path.display(); "[Page::from_dir] Error reading root website path!")
+        }
+
+        root_page.sort_child();
+        for language_root in &mut root_page.language_roots {
+            language_root.sort_child();
+        }
+
+        if let Err(err) = root_page.add_data_pages(source_fs, root_path, config, site_data) {
+            warn!(stage = "load"; "[Page::from_dir] Failed adding pages from data! Error: {err}");
+
+            return None;
+        }
+
+        root_page.special_pages =
+            Self::parse_special_pages(source_fs, root_path, config, &mut skipped_files);
+        root_page.skipped_files = skipped_files;
+
+        if let Err(err) = collect_redirects(&root_page) {
+            warn!(stage = "load"; "[Page::from_dir] Invalid alias! Error: {err}");
+
+            return None;
+        }
+
+        if let Err(err) = root_page.check_paths() {
+            warn!(stage = "load"; "[Page::from_dir] Invalid page path! Error: {err}");
+
+            return None;
         }
 
+        let collisions = root_page.find_collisions(&[]);
+        if !collisions.is_empty() {
+            for collision in collisions {
+                warn!(stage = "load", file:% = collision.output_file.display(); "[Page::from_dir] Output path collision, {collision}!");
+            }
+
+            return None;
+        }
+
+        debug!(
+            stage = "load";
+            "[Page::from_dir] Loaded {} pages from {root_base_path:?}",
+            root_page.all_pages().len()
+        );
+
         Some(root_page)
     }
 
-    pub fn render_all<P: AsRef<Path>>(
-        self,
-        output_dir: &P,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let page_child = Vec::clone(&self.child);
-        self.render(output_dir)?;
-
-        for child in page_child {
-            if child.is_dir_root {
-                child.render_all(output_dir)?;
-            } else {
-                child.render(output_dir)?;
+    /// A page path with a `..` segment is an error, e.g. from a `path` override or a permalink
+    /// pattern, as the page could be written outside of the output directory.
+    fn check_paths(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for page in self.all_pages() {
+            if page.path.split('/').any(|segment| segment == "..") {
+                return Err(format!(
+                    "Path '{}' of {:?} cannot have '..'!",
+                    page.path, page.content.source_path
+                )
+                .into());
             }
         }
 
         Ok(())
     }
 
-    /// `output_dir` expects to be valid and already exist, and is the root of the file that will be rendered.
-    pub fn render<P: AsRef<Path>>(self, output_dir: &P) -> Result<(), Box<dyn std::error::Error>> {
-        let mut output_path = PathBuf::new();
-        output_path.push(output_dir);
+    /// Find every output file that would be written more than once, by the pages of this tree or
+    /// by `generated_files`, given as the output file (relative to the output root) and the
+    /// source that produce it. A page that would be rendered inside the directory of a generated
+    /// file also counts as a collision. The redirect pages of the aliases are always included.
+    pub fn find_collisions(&self, generated_files: &[(PathBuf, PathBuf)]) -> Vec<OutputCollision> {
+        let mut outputs = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+
+        for page in self.all_pages() {
+            outputs
+                .entry(page.output_file())
+                .or_default()
+                .push(page.content.source_path.clone());
+        }
+
+        // An invalid alias fails the build on its own, see `collect_redirects`
+        for redirect in collect_redirects(self).unwrap_or_default() {
+            outputs
+                .entry(redirect.output_file())
+                .or_default()
+                .push(redirect.source);
+        }
+
+        for (output_file, source) in generated_files {
+            outputs
+                .entry(output_file.clone())
+                .or_default()
+                .push(source.clone());
+        }
+
+        let mut collisions: Vec<OutputCollision> = outputs
+            .iter()
+            .filter(|(_, sources)| sources.len() > 1)
+            .map(|(output_file, sources)| OutputCollision {
+                output_file: output_file.clone(),
+                sources: sources.clone(),
+            })
+            .collect();
+
+        for (generated_file, generated_source) in generated_files {
+            for (output_file, sources) in &outputs {
+                if output_file != generated_file && output_file.starts_with(generated_file) {
+                    let mut all_sources = vec![generated_source.clone()];
+                    all_sources.extend(sources.iter().cloned());
+
+                    collisions.push(OutputCollision {
+                        output_file: generated_file.clone(),
+                        sources: all_sources,
+                    });
+                }
+            }
+        }
+
+        collisions
+    }
+
+    /// The final URL of the page on the rendered site, ends with `/` unless it's an ugly URL.
+    pub fn url(&self) -> String {
+        if self.is_special {
+            return format!("/{}.html", self.path);
+        }
+
+        match (self.is_ugly_url, self.is_dir_root, self.path.is_empty()) {
+            (true, true, _) => format!("/{}", join_page_path([self.path.as_str(), "index.html"])),
+            (true, false, _) => format!("/{}.html", self.path),
+            (false, _, true) => String::from("/"),
+            (false, _, false) => format!("/{}/", self.path),
+        }
+    }
+
+    /// The file this page will be rendered to, relative to the output root. Directory root is
+    /// always rendered to `index.html` inside its directory, even with ugly URL, while special
+    /// page is always rendered to `<path>.html`.
+    pub fn output_file(&self) -> PathBuf {
+        if self.is_special || (self.is_ugly_url && !self.is_dir_root) {
+            return PathBuf::from(format!("{}.html", self.path));
+        }
+
+        let mut output_file = PathBuf::from(&self.path);
+        output_file.push("index.html");
+
+        output_file
+    }
+
+    /// A directory page from memory, `path` is the directory path e.g. `blog`, `content` is its
+    /// `_index.md` and `template` is its `template.hbs`.
+    pub fn new_dir(path: &str, content: Content, template: String, config: &SiteConfig) -> Self {
+        Self {
+            path: join_page_path([path]),
+            content,
+            template,
+            is_dir_root: true,
+            is_ugly_url: config.ugly_urls,
+            is_special: false,
+            child: vec![],
+            special_pages: vec![],
+            language_roots: vec![],
+            skipped_files: vec![],
+        }
+    }
+
+    /// Add a content page to this directory with `template` as its `content.hbs`, its path
+    /// follows the permalink of this directory the same as a content file.
+    pub fn add_content(&mut self, content: Content, template: &str, config: &SiteConfig) {
+        let permalink = self
+            .content
+            .metadata
+            .permalink
+            .clone()
+            .or_else(|| config.get_permalink(&self.path).cloned());
+
+        self.push_content(content, template, permalink.as_deref(), config);
+        self.sort_child();
+    }
+
+    /// Add a sub-directory page to this directory, see [`Page::new_dir`].
+    pub fn add_dir(&mut self, dir_page: Page) {
+        self.child.push(dir_page);
+        self.sort_child();
+    }
+
+    /// Sort the child pages by the `sort_by` of this directory.
+    pub fn sort_child(&mut self) {
+        let sort_by = self.content.metadata.sort_by;
+        sort_by.sort_pages(&mut self.child);
+    }
+
+    /// The page path without leading and trailing `/`, e.g. `blog/hello`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn content(&self) -> &Content {
+        &self.content
+    }
+
+    pub fn content_mut(&mut self) -> &mut Content {
+        &mut self.content
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn set_template(&mut self, template: String) {
+        self.template = template;
+    }
+
+    pub fn is_dir_root(&self) -> bool {
+        self.is_dir_root
+    }
+
+    /// The contents and sub-directories of this directory, in their sort order.
+    pub fn child(&self) -> &[Page] {
+        &self.child
+    }
+
+    pub fn child_mut(&mut self) -> &mut Vec<Page> {
+        &mut self.child
+    }
+
+    /// Only filled on the root page.
+    pub fn special_pages(&self) -> &[Page] {
+        &self.special_pages
+    }
+
+    /// Only filled on the root page loaded by [`Page::from_dir`].
+    pub fn skipped_files(&self) -> &[SkippedFile] {
+        &self.skipped_files
+    }
+
+    pub fn special_pages_mut(&mut self) -> &mut Vec<Page> {
+        &mut self.special_pages
+    }
+
+    /// The root pages of every other language, only filled on the root page.
+    pub fn language_roots(&self) -> &[Page] {
+        &self.language_roots
+    }
+
+    pub fn language_roots_mut(&mut self) -> &mut Vec<Page> {
+        &mut self.language_roots
+    }
+
+    /// Render every output of the site to memory, keyed by the output file relative to the
+    /// output root. This function assume that `self` is the root of all of the pages.
+    pub fn render_to_memory(
+        self,
+        renderer: &Renderer,
+    ) -> Result<BTreeMap<PathBuf, Vec<u8>>, Box<dyn std::error::Error>> {
+        self.check_paths()?;
+
+        let collisions = self.find_collisions(&renderer.generated_files());
+        if !collisions.is_empty() {
+            let messages: Vec<String> = collisions
+                .iter()
+                .map(|collision| collision.to_string())
+                .collect();
+
+            return Err(format!("Output path collision, {}!", messages.join(", ")).into());
+        }
+
+        let mut renderer = renderer.clone();
+        renderer.internal_links = InternalLinks::from_page(&self);
+        renderer.site_tree = Some(SectionTree::from(&self));
+        renderer.menus = Menus::from_page(&self, &renderer.config);
+        renderer.translations = Translations::from_page(&self, &renderer.config);
+        renderer.registry = renderer.build_registry();
+        let redirects = collect_redirects(&self)?;
+        let sitemaps = if renderer.config.sitemap {
+            render_sitemaps(&self, &renderer.translations, &renderer.config)?
+        } else {
+            vec![]
+        };
+        let special_pages = self.special_pages.clone();
+        let language_roots = self.language_roots.clone();
+
+        let mut outputs = BTreeMap::new();
+        self.render_tree(&renderer, PageNavigation::default(), &mut outputs)?;
+
+        for language_root in language_roots {
+            // Every language has its own site tree
+            let mut language_renderer = renderer.clone();
+            language_renderer.site_tree = Some(SectionTree::from(&language_root));
+            language_root.render_tree(
+                &language_renderer,
+                PageNavigation::default(),
+                &mut outputs,
+            )?;
+        }
+
+        for special_page in special_pages {
+            let (output_file, output) =
+                special_page.render(&renderer, &PageNavigation::default())?;
+            outputs.insert(output_file, output);
+        }
+
+        for (output_file, output) in render_redirects(&redirects, &renderer.config)? {
+            outputs.insert(output_file, output.into_bytes());
+        }
+
+        for (output_file, output) in sitemaps {
+            outputs.insert(output_file, output.into_bytes());
+        }
+
+        if let Some(css) = renderer.config.markdown.highlight.render_css()? {
+            outputs.insert(
+                PathBuf::from(HighlightConfig::CSS_FILE_NAME),
+                css.into_bytes(),
+            );
+        }
+
+        for (output_file, output) in outputs.iter_mut() {
+            renderer.plugins.output(output_file, output)?;
+        }
+
+        Ok(outputs)
+    }
+
+    /// Render every output of the site to `output_sink`, see [`Page::render_to_memory`].
+    /// This function assume that `self` is the root of all of the pages.
+    pub fn render_all(
+        self,
+        output_sink: &mut dyn OutputSink,
+        renderer: &Renderer,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        write_outputs(&self.render_to_memory(renderer)?, output_sink)
+    }
+
+    fn render_tree(
+        self,
+        renderer: &Renderer,
+        navigation: PageNavigation,
+        outputs: &mut BTreeMap<PathBuf, Vec<u8>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let page_child = Vec::clone(&self.child);
+        let current_ref = PageRef::from(&self);
+        let mut child_ancestors = navigation.ancestors.clone();
+        child_ancestors.push(current_ref.clone());
+
+        let (output_file, output) = self.render(renderer, &navigation)?;
+        outputs.insert(output_file, output);
+
+        // Pages and directories are siblings on their own
+        let (sections, contents): (Vec<Page>, Vec<Page>) =
+            page_child.into_iter().partition(|child| child.is_dir_root);
 
-        if !self.path.is_empty() {
-            output_path.push(self.path.clone());
+        for siblings in [contents, sections] {
+            let sibling_refs: Vec<PageRef> = siblings.iter().map(PageRef::from).collect();
+
+            for (index, child) in siblings.into_iter().enumerate() {
+                let child_navigation = PageNavigation {
+                    section: Some(current_ref.clone()),
+                    ancestors: child_ancestors.clone(),
+                    prev: index
+                        .checked_sub(1)
+                        .and_then(|prev_index| sibling_refs.get(prev_index))
+                        .cloned(),
+                    next: sibling_refs.get(index + 1).cloned(),
+                };
+
+                if child.is_dir_root {
+                    child.render_tree(renderer, child_navigation, outputs)?;
+                } else {
+                    let (output_file, output) = child.render(renderer, &child_navigation)?;
+                    outputs.insert(output_file, output);
+                }
+            }
         }
 
-        fs::create_dir_all(&output_path)?;
-        output_path.push("index.html");
+        Ok(())
+    }
 
-        let current_path = self.path.clone();
-        println!("[Page::render] path {output_path:#?}, current path: {current_path:#?}");
+    /// Render this page alone, returns the output file relative to the output root and the
+    /// rendered HTML.
+    pub fn render(
+        self,
+        renderer: &Renderer,
+        navigation: &PageNavigation,
+    ) -> Result<(PathBuf, Vec<u8>), Box<dyn std::error::Error>> {
+        let output_file = self.output_file();
+        debug!(
+            stage = "render", page = self.path.as_str(), file:% = self.content.source_path.display();
+            "[Page::render] Rendering {output_file:?}"
+        );
 
-        let mut hbs_registry = Handlebars::new();
+        let page_ref = PageRef::from(&self);
+        let translations = renderer.translations.for_page(&self, &renderer.config);
+        let mut hbs_registry = renderer.registry.clone();
         hbs_registry.register_template_string(&self.path, self.template)?;
         let mut render_data = Map::<String, Value>::new();
 
-        render_data.insert("content".into(), to_json(self.content.to_html()));
+        let (content_html, toc) = self.content.to_html_with_toc(renderer)?;
+        render_data.insert("content".into(), to_json(content_html));
+        render_data.insert("toc".into(), to_json(toc));
+        render_data.insert("data".into(), to_json(&renderer.data));
+        render_data.insert(
+            "menus".into(),
+            to_json(renderer.menus.for_url(&page_ref.url)),
+        );
+        render_data.insert(
+            "lang".into(),
+            to_json(
+                self.content
+                    .metadata
+                    .lang
+                    .as_deref()
+                    .unwrap_or(renderer.config.default_language()),
+            ),
+        );
+        render_data.insert("translations".into(), to_json(translations));
+        render_data.insert("page".into(), to_json(page_ref));
+        render_data.insert("section".into(), to_json(&navigation.section));
+        render_data.insert("ancestors".into(), to_json(&navigation.ancestors));
+        render_data.insert("prev".into(), to_json(&navigation.prev));
+        render_data.insert("next".into(), to_json(&navigation.next));
+
+        if let Some(site_tree) = &renderer.site_tree {
+            render_data.insert("site".into(), to_json(site_tree));
+        }
 
         if self.is_dir_root {
             render_data.insert("content_list".into(), to_json(self.child));
         }
 
-        println!("[Page::render] writing file to {output_path:#?}");
-        let output_file = File::create(output_path)?;
-        hbs_registry.render_to_write(&self.path, &render_data, output_file)?;
+        renderer.plugins.render_context(&mut render_data)?;
 
-        Ok(())
+        let mut rendered_html = hbs_registry.render(&self.path, &render_data)?;
+        if renderer.config.relative_urls {
+            rendered_html = rewrite_root_urls(&rendered_html, &output_file);
+        }
+
+        Ok((output_file, rendered_html.into_bytes()))
     }
 }
 
@@ -221,7 +1105,14 @@ impl Page {
 mod page_test {
     use std::path::PathBuf;
 
-    use crate::pages::Page;
+    use crate::{
+        config::SiteConfig,
+        content::Content,
+        data::{DataPages, SiteData},
+        pages::Page,
+        renderer::Renderer,
+        vfs::{DiskFs, MemoryFs},
+    };
 
     const MAIN_DIR: &str = env!("CARGO_MANIFEST_DIR");
 
@@ -238,7 +1129,12 @@ mod page_test {
 
         println!("[page_test::test_with_test_files] reading from file path: {test_path:#?}");
 
-        let page_root = Page::from_dir(&test_path);
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &test_path,
+            &SiteConfig::default(),
+            &SiteData::default(),
+        );
 
         assert!(
             page_root.is_some(),
@@ -255,8 +1151,8 @@ mod page_test {
             "There should be one page other than index!"
         );
         assert_eq!(
-            page_root_unwrapped.path, "/",
-            "Page root should always be '/'!"
+            page_root_unwrapped.path, "",
+            "Page root should always be empty!"
         );
         assert!(
             !page_root_unwrapped.template.is_empty(),
@@ -272,14 +1168,14 @@ mod page_test {
             "There should exactly 1 child (content) of blog page!"
         );
         assert_eq!(
-            blog_page.path, "/blog",
+            blog_page.path, "blog",
             "Blog page should have path of blog!"
         );
 
         // Asserted with the length before
         let test_content = blog_page.child.first().unwrap();
 
-        let mut content_path = String::from("/blog/");
+        let mut content_path = String::from("blog/");
         content_path.push_str(test_content.content.metadata.slug.as_ref().unwrap());
 
         assert_eq!(
@@ -287,4 +1183,205 @@ mod page_test {
             "The path of a content node should be equal to the slug!"
         );
     }
+
+    #[test]
+    fn test_output_collisions() {
+        let mut page_root = Page::from_dir(
+            &DiskFs,
+            &get_path_to_test_files(),
+            &SiteConfig::default(),
+            &SiteData::default(),
+        )
+        .unwrap();
+
+        assert!(
+            page_root.find_collisions(&[]).is_empty(),
+            "Known correct directory structure should not have collision!"
+        );
+
+        // Content with the same slug as the blog directory
+        let mut duplicate_page = page_root.child[0].child[0].clone();
+        duplicate_page.path = "blog".into();
+        page_root.child.push(duplicate_page);
+
+        let collisions = page_root.find_collisions(&[("index.html".into(), "config.toml".into())]);
+
+        assert_eq!(collisions.len(), 2, "Both collisions should be found!");
+        assert!(
+            collisions
+                .iter()
+                .all(|collision| collision.sources.len() == 2),
+            "Collision should list both of the source paths!"
+        );
+    }
+
+    #[test]
+    fn test_permalinks() {
+        let mut config = SiteConfig::default();
+        config
+            .permalinks
+            .insert("/blog/".into(), "/posts/:year/:slug/".into());
+
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &get_path_to_test_files(),
+            &config,
+            &SiteData::default(),
+        )
+        .unwrap();
+        let test_content = &page_root.child[0].child[0];
+
+        assert_eq!(
+            test_content.path, "blog/test-hello",
+            "Content without updated_at should not use the dates of a permalink!"
+        );
+
+        config
+            .permalinks
+            .insert("/blog/".into(), "/posts/:slug/".into());
+
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &get_path_to_test_files(),
+            &config,
+            &SiteData::default(),
+        )
+        .unwrap();
+        let test_content = &page_root.child[0].child[0];
+
+        assert_eq!(
+            test_content.path, "posts/test-hello",
+            "Content path should follow the directory permalink!"
+        );
+    }
+
+    #[test]
+    fn test_ugly_urls() {
+        let config = SiteConfig {
+            ugly_urls: true,
+            ..Default::default()
+        };
+
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &get_path_to_test_files(),
+            &config,
+            &SiteData::default(),
+        )
+        .unwrap();
+        let blog_page = &page_root.child[0];
+        let test_content = &blog_page.child[0];
+
+        assert_eq!(page_root.url(), "/index.html");
+        assert_eq!(blog_page.url(), "/blog/index.html");
+        assert_eq!(test_content.url(), "/blog/test-hello.html");
+        assert_eq!(
+            test_content.output_file(),
+            PathBuf::from("blog/test-hello.html"),
+            "Content should be rendered to a flat file!"
+        );
+    }
+
+    #[test]
+    fn test_special_pages() {
+        let page_root = Page::from_dir(
+            &DiskFs,
+            &get_path_to_test_files(),
+            &SiteConfig::default(),
+            &SiteData::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            page_root.special_pages.len(),
+            1,
+            "404.md should be parsed as special page!"
+        );
+        assert!(
+            page_root.child.iter().all(|child| !child.is_special),
+            "Special page should not be listed with the other pages!"
+        );
+
+        let not_found_page = &page_root.special_pages[0];
+
+        assert_eq!(not_found_page.output_file(), PathBuf::from("404.html"));
+        assert_eq!(not_found_page.url(), "/404.html");
+    }
+
+    #[test]
+    fn test_data_pages() {
+        let config = SiteConfig {
+            data_pages: vec![DataPages {
+                data: "posts.post".into(),
+                section: "blog".into(),
+                template: "blog/post.hbs".into(),
+            }],
+            ..Default::default()
+        };
+
+        let site_data = SiteData::from_dir(&DiskFs, &get_path_to_test_files()).unwrap();
+        let page_root =
+            Page::from_dir(&DiskFs, &get_path_to_test_files(), &config, &site_data).unwrap();
+        let blog_page = &page_root.child[0];
+        let data_page = blog_page
+            .child
+            .iter()
+            .find(|child| child.content.metadata.title == Some("From Data".into()))
+            .expect("Data record should be added to its directory!");
+
+        assert_eq!(data_page.url(), "/blog/from-data/");
+        assert_eq!(
+            data_page.content.metadata.updated_at.to_rfc3339(),
+            "2024-05-03T00:00:00+00:00"
+        );
+        assert_eq!(
+            data_page.content.to_html(&Renderer::default()).unwrap(),
+            "<p>This page is generated from <code>_data/posts.toml</code>!</p>\n"
+        );
+    }
+
+    #[test]
+    fn parent_dir_paths_rejected() {
+        let mut config = SiteConfig::default();
+        config
+            .permalinks
+            .insert("blog".into(), "/blog/:slug/".into());
+        let source_fs = MemoryFs::new()
+            .with_file(&"_index.md", "Home")
+            .with_file(&"template.hbs", "{{{content}}}")
+            .with_file(&"blog/_index.md", "Blog")
+            .with_file(&"blog/template.hbs", "{{{content}}}")
+            .with_file(&"blog/content.hbs", "{{{content}}}")
+            .with_file(&"blog/up.md", "---\nslug = \"../..\"\n---\nUp");
+
+        assert!(
+            Page::from_dir(&source_fs, &"", &config, &SiteData::default()).is_none(),
+            "Permalink expanded to '..' should be rejected on load!"
+        );
+
+        let mut root_page = Page::new_dir(
+            "",
+            Content::from_markdown("Home".into(), PathBuf::from("_index.md")),
+            "{{{content}}}".into(),
+            &config,
+        );
+        root_page.add_content(
+            Content::from_markdown(
+                "---\npath = \"../../x\"\n---\nEscape".into(),
+                PathBuf::from("escape.md"),
+            ),
+            "{{{content}}}",
+            &config,
+        );
+
+        let err = root_page
+            .render_to_memory(&Renderer::default())
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            err.contains("escape.md"),
+            "Error should name the source file! Got: {err}"
+        );
+    }
 }
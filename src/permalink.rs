@@ -0,0 +1,132 @@
+use log::warn;
+
+use crate::{preparser::ContentMetadata, slugify::SlugifyConfig};
+
+const DATE_TOKENS: [&str; 3] = [":year", ":month", ":day"];
+
+/// Join the segments of a page path, ignoring every empty segment and hanging `/`, so the result
+/// never starts or ends with `/`.
+pub fn join_page_path<'a, I: IntoIterator<Item = &'a str>>(segments: I) -> String {
+    segments
+        .into_iter()
+        .flat_map(|segment| segment.split('/'))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// Expand a permalink pattern, e.g. `/blog/:year/:month/:slug/`, into the page path of a content
+/// on the directory `section`. The supported tokens are `:section`, `:slug`, `:title`, `:author`,
+/// `:year`, `:month` and `:day`, the dates are taken from `updated_at`.
+///
+/// Returns `None` if the pattern has a date token but `updated_at` is not set on the content, as
+/// the fallback (the current time) would move the page on every build.
+pub fn expand_permalink(
+    pattern: &str,
+    section: &str,
+    metadata: &ContentMetadata,
+    slugify_config: &SlugifyConfig,
+) -> Option<String> {
+    if !metadata.is_date_set && DATE_TOKENS.iter().any(|token| pattern.contains(token)) {
+        return None;
+    }
+
+    let slugify_field =
+        |field: &Option<String>| slugify_config.slugify(field.as_deref().unwrap_or_default());
+
+    let tokens = [
+        (":section", section.to_owned()),
+        (":slug", metadata.slug.clone().unwrap_or_default()),
+        (":title", slugify_field(&metadata.title)),
+        (":author", slugify_field(&metadata.author)),
+        (":year", metadata.updated_at.format("%Y").to_string()),
+        (":month", metadata.updated_at.format("%m").to_string()),
+        (":day", metadata.updated_at.format("%d").to_string()),
+    ];
+
+    for (token_start, _) in pattern.match_indices(':') {
+        if !tokens
+            .iter()
+            .any(|(token, _)| pattern[token_start..].starts_with(token))
+        {
+            warn!(stage = "load"; "[permalink::expand_permalink] Unknown token at {token_start} of permalink '{pattern}'!");
+        }
+    }
+
+    let mut expanded_path = pattern.to_owned();
+    for (token, value) in &tokens {
+        expanded_path = expanded_path.replace(token, value);
+    }
+
+    Some(join_page_path([expanded_path.as_str()]))
+}
+
+#[cfg(test)]
+mod permalink_test {
+    use chrono::TimeZone;
+
+    use crate::{
+        permalink::{expand_permalink, join_page_path},
+        preparser::ContentMetadata,
+        slugify::SlugifyConfig,
+    };
+
+    #[test]
+    fn page_path_joined() {
+        assert_eq!(join_page_path(["", "about"]), "about");
+        assert_eq!(join_page_path(["/blog/", "/hello/"]), "blog/hello");
+    }
+
+    #[test]
+    fn permalink_expanded() {
+        let metadata = ContentMetadata {
+            title: Some("Hello World!".into()),
+            slug: Some("test-hello".into()),
+            updated_at: chrono::Utc.with_ymd_and_hms(2024, 5, 3, 0, 0, 0).unwrap(),
+            is_date_set: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            expand_permalink(
+                "/blog/:year/:month/:slug/",
+                "blog",
+                &metadata,
+                &SlugifyConfig::default()
+            ),
+            Some("blog/2024/05/test-hello".into())
+        );
+        assert_eq!(
+            expand_permalink(
+                "/docs/:section/:title/",
+                "guide/intro",
+                &metadata,
+                &SlugifyConfig::default()
+            ),
+            Some("docs/guide/intro/hello-world".into())
+        );
+    }
+
+    #[test]
+    fn permalink_without_date() {
+        let metadata = ContentMetadata {
+            slug: Some("test-hello".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            expand_permalink(
+                "/blog/:year/:slug/",
+                "blog",
+                &metadata,
+                &SlugifyConfig::default()
+            ),
+            None,
+            "Date token should not fall back to the current time!"
+        );
+        assert_eq!(
+            expand_permalink("/blog/:slug/", "blog", &metadata, &SlugifyConfig::default()),
+            Some("blog/test-hello".into())
+        );
+    }
+}
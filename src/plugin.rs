@@ -0,0 +1,248 @@
+use std::{path::Path, sync::Arc};
+
+use pulldown_cmark::Event;
+use serde_json::{Map, Value};
+
+use crate::{content::Content, pages::Page};
+
+/// Hooks into the stages of a build, registered with [`crate::site::SiteBuilder::plugin`]. Every
+/// hook does nothing by default, so a plugin only implements the stages it needs. Plugins are
+/// called in the order they are registered.
+pub trait Plugin: Send + Sync {
+    /// Used on the diagnostics and errors.
+    fn name(&self) -> &str;
+
+    /// Called for every content once the page tree is loaded, the page paths are already set.
+    fn on_content_loaded(&self, _content: &mut Content) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Called with the markdown events of every content, after internal links are resolved and
+    /// before the code blocks are highlighted and the headings get their ids.
+    fn on_markdown_events<'a>(
+        &self,
+        events: Vec<Event<'a>>,
+    ) -> Result<Vec<Event<'a>>, Box<dyn std::error::Error>> {
+        Ok(events)
+    }
+
+    /// Called once with the root page, after every content is loaded.
+    fn on_page_tree(&self, _root_page: &mut Page) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Called with the values given to the template of every page, right before rendering it.
+    fn on_render_context(
+        &self,
+        _render_data: &mut Map<String, Value>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Called for every output file, including redirects and generated files, before it's
+    /// written. `output_file` is relative to the output root.
+    fn on_output(
+        &self,
+        _output_file: &Path,
+        _output: &mut Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// Every plugin of a site, in the order they are registered.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl std::fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.plugins.iter().map(|plugin| plugin.name()))
+            .finish()
+    }
+}
+
+/// Add the plugin name to the error of a hook.
+fn plugin_error(
+    plugin: &dyn Plugin,
+    err: Box<dyn std::error::Error>,
+) -> Box<dyn std::error::Error> {
+    format!("Plugin '{}' failed! {err}", plugin.name()).into()
+}
+
+impl Plugins {
+    pub fn register(&mut self, plugin: Arc<dyn Plugin>) {
+        self.plugins.push(plugin);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    fn content_loaded(&self, page: &mut Page) -> Result<(), Box<dyn std::error::Error>> {
+        for plugin in &self.plugins {
+            plugin
+                .on_content_loaded(page.content_mut())
+                .map_err(|err| plugin_error(plugin.as_ref(), err))?;
+        }
+
+        for child in page.child_mut() {
+            self.content_loaded(child)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run `on_content_loaded` on every page, including the special pages and the pages of every
+    /// other language, then `on_page_tree`.
+    pub fn page_tree(&self, root_page: &mut Page) -> Result<(), Box<dyn std::error::Error>> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        self.content_loaded(root_page)?;
+        for special_page in root_page.special_pages_mut() {
+            self.content_loaded(special_page)?;
+        }
+        for language_root in root_page.language_roots_mut() {
+            self.content_loaded(language_root)?;
+        }
+
+        for plugin in &self.plugins {
+            plugin
+                .on_page_tree(root_page)
+                .map_err(|err| plugin_error(plugin.as_ref(), err))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn markdown_events<'a>(
+        &self,
+        mut events: Vec<Event<'a>>,
+    ) -> Result<Vec<Event<'a>>, Box<dyn std::error::Error>> {
+        for plugin in &self.plugins {
+            events = plugin
+                .on_markdown_events(events)
+                .map_err(|err| plugin_error(plugin.as_ref(), err))?;
+        }
+
+        Ok(events)
+    }
+
+    pub fn render_context(
+        &self,
+        render_data: &mut Map<String, Value>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for plugin in &self.plugins {
+            plugin
+                .on_render_context(render_data)
+                .map_err(|err| plugin_error(plugin.as_ref(), err))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn output(
+        &self,
+        output_file: &Path,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for plugin in &self.plugins {
+            plugin
+                .on_output(output_file, output)
+                .map_err(|err| plugin_error(plugin.as_ref(), err))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod plugin_test {
+    use std::path::{Path, PathBuf};
+
+    use pulldown_cmark::{CowStr, Event};
+    use serde_json::{Map, Value};
+
+    use crate::{config::SiteConfig, content::Content, pages::Page, plugin::Plugin, site::Site};
+
+    struct TestPlugin;
+
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn on_content_loaded(
+            &self,
+            content: &mut Content,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            content.metadata.title = content
+                .metadata
+                .title
+                .as_ref()
+                .map(|title| title.to_uppercase());
+
+            Ok(())
+        }
+
+        fn on_markdown_events<'a>(
+            &self,
+            events: Vec<Event<'a>>,
+        ) -> Result<Vec<Event<'a>>, Box<dyn std::error::Error>> {
+            Ok(events
+                .into_iter()
+                .map(|event| match event {
+                    Event::Text(text) => Event::Text(CowStr::from(text.replace("cat", "dog"))),
+                    event => event,
+                })
+                .collect())
+        }
+
+        fn on_render_context(
+            &self,
+            render_data: &mut Map<String, Value>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            render_data.insert("greeting".into(), "Hi".into());
+
+            Ok(())
+        }
+
+        fn on_output(
+            &self,
+            _output_file: &Path,
+            output: &mut Vec<u8>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            output.extend_from_slice(b"<!-- analytics -->");
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn plugin_hooks_called() {
+        let config = SiteConfig::default();
+        let root_page = Page::new_dir(
+            "",
+            Content::from_markdown("A cat.".into(), "home.md".into()),
+            "{{greeting}} {{page.title}} {{{content}}}".into(),
+            &config,
+        );
+
+        let site = Site::builder()
+            .root_page(root_page)
+            .plugin(TestPlugin)
+            .build()
+            .unwrap();
+        let outputs = site.render_to_memory().unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&outputs[&PathBuf::from("index.html")]),
+            "Hi HOME <p>A dog.</p>\n<!-- analytics -->",
+            "Every hook should be called!"
+        );
+    }
+}
@@ -1,13 +1,58 @@
+use std::collections::HashMap;
+
+use log::trace;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    config::MarkdownOverrides, menu::MenuEntry, navigation::SortBy, slugify::SlugifyConfig,
+};
+
 #[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct ContentMetadata {
-    pub(crate) title: Option<String>,
-    pub(crate) slug: Option<String>,
-    pub(crate) author: Option<String>,
-    pub(crate) tags: Option<Vec<String>>,
-    #[serde(with = "toml_datetime_compat", default = "chrono::Utc::now")]
-    pub(crate) updated_at: chrono::DateTime<chrono::Utc>,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub author: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// Serialized as RFC 3339 string for the templates
+    #[serde(
+        deserialize_with = "toml_datetime_compat::deserialize",
+        default = "chrono::Utc::now"
+    )]
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Overrides the whole page path, e.g. `/about-me/`
+    pub path: Option<String>,
+    /// Only used on `_index.md`, the permalink pattern of every content on the directory
+    pub permalink: Option<String>,
+    /// Old URLs of the page, each of them will be redirected to the page
+    pub aliases: Option<Vec<String>>,
+    /// Used to order the pages when the directory is sorted by weight
+    pub weight: Option<i64>,
+    /// Entries of the named menus that link to this page, e.g. `menu = { main = { weight = 10 } }`
+    #[serde(default, skip_serializing)]
+    pub menu: Option<HashMap<String, MenuEntry>>,
+    /// The whole record of a page generated from a data file, see [`crate::data::DataPages`]
+    #[serde(skip_deserializing)]
+    pub record: Option<serde_json::Value>,
+    /// Only used on `_index.md`, how the pages on the directory are ordered
+    #[serde(default, skip_serializing)]
+    pub sort_by: SortBy,
+    /// Per page override of the site markdown extensions
+    #[serde(default, skip_serializing)]
+    pub markdown: Option<MarkdownOverrides>,
+    /// Language code of the content, taken from its file name e.g. `id` for `post.id.md`, or
+    /// the default language, see [`crate::config::SiteConfig::languages`]
+    #[serde(skip_deserializing)]
+    pub lang: Option<String>,
+    /// The source path shared by every translation of the content, see
+    /// [`crate::i18n::translation_key`]
+    #[serde(skip)]
+    pub(crate) translation_key: Option<std::path::PathBuf>,
+    /// True if `slug` is not set on the front matter, and is taken from the file name
+    #[serde(skip)]
+    pub(crate) is_slug_generated: bool,
+    /// True if `updated_at` is set on the front matter, instead of falling back to the current time
+    #[serde(skip)]
+    pub(crate) is_date_set: bool,
 }
 
 impl ContentMetadata {
@@ -44,16 +89,19 @@ impl ContentMetadata {
                 metadata_toml.push('\n');
             }
 
-            println!(
-                "[ContentMetadata::preprocess_content_metadata] Metadata field:\n{metadata_toml}"
-            );
-
-            parsed_metadata = toml::from_str(&metadata_toml).unwrap_or(ContentMetadata::default());
+            if let Ok(metadata) = toml::from_str::<ContentMetadata>(&metadata_toml) {
+                parsed_metadata = metadata;
+                // Only known after parsing, as `updated_at` defaults to the current time
+                parsed_metadata.is_date_set = metadata_toml
+                    .parse::<toml::Table>()
+                    .is_ok_and(|metadata_table| metadata_table.contains_key("updated_at"));
+            }
             remaining_content = content_lines.collect::<Vec<&str>>().join("\n");
         }
 
-        println!(
-            "[ContentMetadata::preprocess_content_metadata] Parsed metadata:\n{parsed_metadata:#?}"
+        trace!(
+            stage = "load";
+            "[ContentMetadata::preprocess_content_metadata] Parsed metadata of '{file_name}': {parsed_metadata:?}"
         );
 
         // Default value handling
@@ -62,12 +110,13 @@ impl ContentMetadata {
         // as there should always be slug (the file name itself)
         // TODO: Make this better somehow?
         if parsed_metadata.slug.is_none() {
-            println!("[ContentMetadata::preprocess_content_metadata] No slug, setting it with file_name:\n{file_name}");
+            trace!(stage = "load"; "[ContentMetadata::preprocess_content_metadata] No slug, setting it with file name '{file_name}'");
             parsed_metadata.slug = Some(file_name.clone());
+            parsed_metadata.is_slug_generated = true;
         }
 
         if parsed_metadata.title.is_none() {
-            println!("[ContentMetadata::preprocess_content_metadata] No title, setting it with file_name:\n{file_name}");
+            trace!(stage = "load"; "[ContentMetadata::preprocess_content_metadata] No title, setting it with file name '{file_name}'");
             parsed_metadata.title = Some(file_name.clone())
         }
 
@@ -75,11 +124,22 @@ impl ContentMetadata {
         // remaining_content
         (parsed_metadata, remaining_content.trim().to_owned())
     }
+
+    /// Slugify the slug taken from the file name, or any slug if `normalize_user_slugs` is set.
+    pub fn normalize_slug(&mut self, slugify_config: &SlugifyConfig) {
+        if !self.is_slug_generated && !slugify_config.normalize_user_slugs {
+            return;
+        }
+
+        if let Some(slug) = &self.slug {
+            self.slug = Some(slugify_config.slugify(slug));
+        }
+    }
 }
 
 #[cfg(test)]
 mod preprocess_test {
-    use crate::preparser::ContentMetadata;
+    use crate::{preparser::ContentMetadata, slugify::SlugifyConfig};
 
     #[test]
     fn metadata_parsed() {
@@ -109,6 +169,17 @@ title = "Halo!"
             rest_of_test_data, "# Rest of it",
             "Failed to return the rest of the data!"
         );
+        assert!(
+            !metadata.is_date_set,
+            "Missing updated_at should not be marked as set!"
+        );
+
+        let (metadata, _) = ContentMetadata::preprocess_content_metadata(
+            "---\nupdated_at = 2024-05-03T00:00:00Z\n---\n".into(),
+            "test-slug".into(),
+        );
+
+        assert!(metadata.is_date_set, "Set updated_at should be marked!");
     }
 
     #[test]
@@ -136,4 +207,39 @@ title = "Halo!"
             "Failed to ignore metadata header!"
         );
     }
+
+    #[test]
+    fn generated_slug_normalized() {
+        let mut slugify_config = SlugifyConfig::default();
+
+        let (mut metadata, _) =
+            ContentMetadata::preprocess_content_metadata("Hi".into(), "Hello World!".into());
+        metadata.normalize_slug(&slugify_config);
+
+        assert_eq!(
+            metadata.slug,
+            Some("hello-world".into()),
+            "Slug from file name should be slugified!"
+        );
+
+        let test_data = "---\nslug = \"My Slug\"\n---\n\nHi";
+        let (mut metadata, _) =
+            ContentMetadata::preprocess_content_metadata(test_data.into(), "file".into());
+        metadata.normalize_slug(&slugify_config);
+
+        assert_eq!(
+            metadata.slug,
+            Some("My Slug".into()),
+            "User slug should be kept by default!"
+        );
+
+        slugify_config.normalize_user_slugs = true;
+        metadata.normalize_slug(&slugify_config);
+
+        assert_eq!(
+            metadata.slug,
+            Some("my-slug".into()),
+            "User slug should be slugified if configured!"
+        );
+    }
 }
@@ -0,0 +1,246 @@
+use std::{
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SiteConfig, links::relative_to, pages::Page};
+
+/// Extra redirect files generated from the `aliases` of every content, set on `config.toml` as
+/// `[redirects]`. The HTML redirect pages of the aliases are always generated.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct RedirectConfig {
+    /// Write a Netlify/Cloudflare Pages style `_redirects` file
+    pub redirects_file: bool,
+    /// Write an nginx `map` snippet, to be included on the nginx config
+    pub nginx_map: bool,
+}
+
+impl RedirectConfig {
+    pub const REDIRECTS_FILE_NAME: &'static str = "_redirects";
+    pub const NGINX_MAP_FILE_NAME: &'static str = "redirects.nginx.map";
+
+    /// Returns the file name of every redirect file enabled.
+    pub fn get_file_names(&self) -> Vec<&'static str> {
+        let mut file_names = Vec::new();
+
+        if self.redirects_file {
+            file_names.push(Self::REDIRECTS_FILE_NAME);
+        }
+        if self.nginx_map {
+            file_names.push(Self::NGINX_MAP_FILE_NAME);
+        }
+
+        file_names
+    }
+}
+
+/// One redirect from an alias to the URL of the page.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    /// The content that has the alias
+    pub source: PathBuf,
+}
+
+impl Redirect {
+    /// Make sure the alias is an URL starting with `/`, and ends with `/` unless it's a file.
+    fn normalize_alias(alias: &str) -> String {
+        let alias = alias.trim().trim_matches('/');
+
+        if alias.is_empty() {
+            String::from("/")
+        } else if Path::new(alias).extension().is_some() {
+            format!("/{alias}")
+        } else {
+            format!("/{alias}/")
+        }
+    }
+
+    /// The file of the redirect page, relative to the output root.
+    pub fn output_file(&self) -> PathBuf {
+        let mut output_file = PathBuf::from(self.from.trim_start_matches('/'));
+
+        if self.from.ends_with('/') {
+            output_file.push("index.html");
+        }
+
+        output_file
+    }
+
+    pub fn to_html(&self) -> String {
+        let to = self.to.replace('&', "&amp;").replace('"', "&quot;");
+
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="UTF-8" />
+    <title>Redirecting to {to}</title>
+    <link rel="canonical" href="{to}" />
+    <meta http-equiv="refresh" content="0; url={to}" />
+  </head>
+  <body>
+    <a href="{to}">Click here if you are not redirected.</a>
+  </body>
+</html>
+"#
+        )
+    }
+}
+
+/// Collect every alias of the pages on the tree of `root_page`. An alias with a `..` component
+/// is an error, as its redirect page could be written outside of the output directory.
+pub fn collect_redirects(root_page: &Page) -> Result<Vec<Redirect>, Box<dyn std::error::Error>> {
+    let mut redirects = Vec::new();
+
+    for page in root_page.all_pages() {
+        for alias in page.content.metadata.aliases.iter().flatten() {
+            if Path::new(alias)
+                .components()
+                .any(|component| component == Component::ParentDir)
+            {
+                return Err(format!(
+                    "Alias '{alias}' of {:?} cannot have '..'!",
+                    page.content.source_path
+                )
+                .into());
+            }
+
+            redirects.push(Redirect {
+                from: Redirect::normalize_alias(alias),
+                to: page.url(),
+                source: page.content.source_path.clone(),
+            });
+        }
+    }
+
+    redirects.sort_by(|first, second| first.from.cmp(&second.from));
+
+    Ok(redirects)
+}
+
+/// Render the redirect page of every alias, and the enabled redirect files, returns the output
+/// file relative to the output root and its content.
+pub fn render_redirects(
+    redirects: &[Redirect],
+    site_config: &SiteConfig,
+) -> Result<Vec<(PathBuf, String)>, Box<dyn std::error::Error>> {
+    let config = &site_config.redirects;
+    let mut outputs = Vec::new();
+
+    for redirect in redirects {
+        let output_file = redirect.output_file();
+
+        debug!(
+            stage = "render", page = redirect.from.as_str();
+            "[redirect::render_redirects] rendering redirect from {} to {}",
+            redirect.from, redirect.to
+        );
+
+        let html = if site_config.relative_urls {
+            let relative_redirect = Redirect {
+                to: relative_to(&redirect.to, &output_file),
+                ..redirect.clone()
+            };
+            relative_redirect.to_html()
+        } else {
+            redirect.to_html()
+        };
+
+        outputs.push((output_file, html));
+    }
+
+    if config.redirects_file {
+        let mut redirects_file = String::new();
+        for redirect in redirects {
+            writeln!(redirects_file, "{} {} 301", redirect.from, redirect.to)?;
+        }
+
+        outputs.push((
+            PathBuf::from(RedirectConfig::REDIRECTS_FILE_NAME),
+            redirects_file,
+        ));
+    }
+
+    if config.nginx_map {
+        let mut nginx_map = String::from("map $request_uri $redirect_uri {\n");
+        for redirect in redirects {
+            writeln!(nginx_map, "    {} {};", redirect.from, redirect.to)?;
+        }
+        nginx_map.push_str("}\n");
+
+        outputs.push((
+            PathBuf::from(RedirectConfig::NGINX_MAP_FILE_NAME),
+            nginx_map,
+        ));
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod redirect_test {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::SiteConfig,
+        content::Content,
+        pages::Page,
+        redirect::{collect_redirects, Redirect},
+    };
+
+    #[test]
+    fn alias_output_file() {
+        let redirect = Redirect {
+            from: Redirect::normalize_alias("old/path"),
+            to: "/blog/hello/".into(),
+            source: "hello.md".into(),
+        };
+
+        assert_eq!(redirect.from, "/old/path/");
+        assert_eq!(redirect.output_file(), PathBuf::from("old/path/index.html"));
+        assert!(
+            redirect
+                .to_html()
+                .contains(r#"<link rel="canonical" href="/blog/hello/" />"#),
+            "Redirect page should have canonical link!"
+        );
+
+        let redirect = Redirect {
+            from: Redirect::normalize_alias("/old/page.html"),
+            ..redirect
+        };
+
+        assert_eq!(redirect.output_file(), PathBuf::from("old/page.html"));
+    }
+
+    #[test]
+    fn alias_outside_output_rejected() {
+        let config = SiteConfig::default();
+        let mut root_page = Page::new_dir(
+            "",
+            Content::from_markdown("Home".into(), "_index.md".into()),
+            "{{{content}}}".into(),
+            &config,
+        );
+        root_page.add_content(
+            Content::from_markdown(
+                "---\naliases = [\"../../outside\"]\n---\nHello".into(),
+                "hello.md".into(),
+            ),
+            "{{{content}}}",
+            &config,
+        );
+
+        let err = collect_redirects(&root_page).unwrap_err().to_string();
+        assert!(
+            err.contains("hello.md"),
+            "Error should name the source file! {err}"
+        );
+    }
+}
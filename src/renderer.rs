@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use handlebars::Handlebars;
+
+use crate::{
+    config::SiteConfig, data::SiteData, helpers::register_helpers, highlight::HighlightConfig,
+    i18n::Translations, links::InternalLinks, menu::Menus, navigation::SectionTree,
+    plugin::Plugins, script_helper::ScriptHelpers, shortcode::Shortcodes, sitemap::sitemap_file,
+    vfs::SourceFs,
+};
+
+/// Everything shared by all of the pages of a site while rendering.
+#[derive(Debug, Default, Clone)]
+pub struct Renderer {
+    pub config: SiteConfig,
+    pub shortcodes: Shortcodes,
+    pub script_helpers: ScriptHelpers,
+    pub data: SiteData,
+    pub plugins: Plugins,
+    /// The registry with every helper, each page registers its template on its own clone.
+    /// Filled when rendering starts, see [`Renderer::build_registry`]
+    pub registry: Handlebars<'static>,
+    /// Filled from the page tree when rendering starts
+    pub internal_links: InternalLinks,
+    /// Filled from the page tree when rendering starts
+    pub site_tree: Option<SectionTree>,
+    /// Filled from the config and the page tree when rendering starts
+    pub menus: Menus,
+    /// Filled from the page tree when rendering starts
+    pub translations: Translations,
+}
+
+impl Renderer {
+    /// This function assume that `root_path` is the root of all of the pages.
+    pub fn from_dir<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            config: SiteConfig::from_dir(source_fs, root_path)?,
+            shortcodes: Shortcodes::from_dir(source_fs, root_path)?,
+            script_helpers: ScriptHelpers::from_dir(source_fs, root_path)?,
+            data: SiteData::from_dir(source_fs, root_path)?,
+            plugins: Plugins::default(),
+            registry: Handlebars::new(),
+            internal_links: InternalLinks::default(),
+            site_tree: None,
+            menus: Menus::default(),
+            translations: Translations::default(),
+        })
+    }
+
+    /// Build the Handlebars registry with the built-in helpers and the script helpers, a script
+    /// helper with the same name as a built-in helper replaces it.
+    pub fn build_registry(&self) -> Handlebars<'static> {
+        let mut registry = Handlebars::new();
+        register_helpers(&mut registry, &self.config);
+        self.script_helpers.register_all(&mut registry);
+
+        registry
+    }
+
+    /// Files written to the output other than the pages, given as the output file (relative to
+    /// the output root) and the source that produce it.
+    pub fn generated_files(&self) -> Vec<(PathBuf, PathBuf)> {
+        let mut generated_files = Vec::new();
+        let highlight = &self.config.markdown.highlight;
+
+        if highlight.enabled && highlight.css_classes {
+            generated_files.push((
+                PathBuf::from(HighlightConfig::CSS_FILE_NAME),
+                PathBuf::from(SiteConfig::FILE_NAME),
+            ));
+        }
+
+        for file_name in self.config.redirects.get_file_names() {
+            generated_files.push((
+                PathBuf::from(file_name),
+                PathBuf::from(SiteConfig::FILE_NAME),
+            ));
+        }
+
+        if self.config.sitemap {
+            let languages = std::iter::once(self.config.default_language())
+                .chain(self.config.other_languages());
+
+            for lang in languages {
+                generated_files.push((
+                    sitemap_file(&self.config, lang),
+                    PathBuf::from(SiteConfig::FILE_NAME),
+                ));
+            }
+        }
+
+        generated_files
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::pages::Page;
+
+/// A source file or directory that is not rendered, and why.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct SkippedFile {
+    pub file: PathBuf,
+    pub reason: String,
+}
+
+impl SkippedFile {
+    pub fn new<P: AsRef<Path>>(file: &P, reason: impl Into<String>) -> Self {
+        Self {
+            file: file.as_ref().to_path_buf(),
+            reason: reason.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct StageTiming {
+    /// `load`, `render`, `write`, or any stage added by the caller e.g. `check-links`
+    pub stage: String,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct OutputSize {
+    /// Relative to the output root
+    pub file: PathBuf,
+    pub size: u64,
+}
+
+/// What happened on a build, returned by [`crate::site::Site::render_to_sink`], and written by
+/// the CLI as `build-report.json` with `--report`.
+#[derive(Debug, Default, Serialize, PartialEq, Clone)]
+pub struct BuildReport {
+    /// Pages rendered on each directory, keyed by the directory URL path e.g. `/blog/`, the
+    /// special pages are counted on `/`
+    pub sections: BTreeMap<String, usize>,
+    pub skipped: Vec<SkippedFile>,
+    pub stages: Vec<StageTiming>,
+    /// The biggest HTML outputs, biggest first
+    pub biggest_pages: Vec<OutputSize>,
+    pub output_files: usize,
+    /// Size of every output in bytes
+    pub total_size: u64,
+}
+
+impl BuildReport {
+    /// How many pages are kept on [`BuildReport::biggest_pages`].
+    pub const BIGGEST_PAGES: usize = 10;
+
+    /// Count the pages of every directory, and the skipped files found while loading. This
+    /// function assume that `root_page` is the root of all of the pages.
+    pub fn from_page(root_page: &Page) -> Self {
+        let mut sections = BTreeMap::new();
+
+        // Content paths can be anywhere with permalinks, so count them on their directory
+        for page in root_page.all_pages() {
+            if !page.is_dir_root() {
+                continue;
+            }
+
+            let section = if page.path().is_empty() {
+                "/".to_owned()
+            } else {
+                format!("/{}/", page.path())
+            };
+            let pages = 1 + page
+                .child()
+                .iter()
+                .filter(|child| !child.is_dir_root())
+                .count();
+
+            *sections.entry(section).or_default() += pages;
+        }
+        *sections.entry("/".to_owned()).or_default() += root_page.special_pages().len();
+
+        Self {
+            sections,
+            skipped: root_page.skipped_files().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_stage(&mut self, stage: &str, duration: Duration) {
+        self.stages.push(StageTiming {
+            stage: stage.to_owned(),
+            duration_ms: duration.as_secs_f64() * 1000.0,
+        });
+    }
+
+    /// Add the sizes of every output, see [`crate::pages::Page::render_to_memory`].
+    pub fn add_outputs(&mut self, outputs: &BTreeMap<PathBuf, Vec<u8>>) {
+        let mut pages: Vec<OutputSize> = outputs
+            .iter()
+            .filter(|(file, _)| {
+                file.extension()
+                    .is_some_and(|extension| extension == "html")
+            })
+            .map(|(file, output)| OutputSize {
+                file: file.clone(),
+                size: output.len() as u64,
+            })
+            .collect();
+        // Stable sort, so pages of the same size keep their path order
+        pages.sort_by_key(|page| std::cmp::Reverse(page.size));
+        pages.truncate(Self::BIGGEST_PAGES);
+
+        self.biggest_pages = pages;
+        self.output_files += outputs.len();
+        self.total_size += outputs
+            .values()
+            .map(|output| output.len() as u64)
+            .sum::<u64>();
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod report_test {
+    use std::path::PathBuf;
+
+    use crate::{
+        report::SkippedFile,
+        site::Site,
+        vfs::{MemoryFs, SourceFs},
+    };
+
+    #[test]
+    fn build_report_collected() {
+        let source_fs = MemoryFs::new()
+            .with_file(&"_index.md", "Home")
+            .with_file(&"template.hbs", "{{{content}}}")
+            .with_file(&"404.md", "Not found")
+            .with_file(&"404.hbs", "{{{content}}}")
+            .with_file(&"blog/_index.md", "Blog")
+            .with_file(&"blog/template.hbs", "{{{content}}}")
+            .with_file(&"blog/content.hbs", "<article>{{{content}}}</article>")
+            .with_file(&"blog/first.md", "First post, the longest one.")
+            .with_file(&"blog/second.md", "Second")
+            .with_file(&"notes/_index.md", "Notes")
+            .with_file(&"notes/draft.md", "Draft");
+
+        let site = Site::builder().source_fs(source_fs).build().unwrap();
+        let mut output_fs = MemoryFs::new();
+        let report = site.render_to_sink(&mut output_fs).unwrap();
+
+        assert_eq!(
+            report.sections["/"], 2,
+            "Special page should be on the root!"
+        );
+        assert_eq!(report.sections["/blog/"], 3);
+        assert_eq!(
+            report.skipped,
+            vec![SkippedFile::new(&"notes", "no template.hbs")]
+        );
+        assert_eq!(
+            report
+                .stages
+                .iter()
+                .map(|stage| stage.stage.as_str())
+                .collect::<Vec<_>>(),
+            vec!["load", "render", "write"]
+        );
+        assert_eq!(
+            report.biggest_pages[0].file,
+            PathBuf::from("blog/first/index.html")
+        );
+        assert_eq!(report.output_files, 5);
+        assert_eq!(
+            report.total_size,
+            output_fs
+                .files()
+                .values()
+                .map(|output| output.len() as u64)
+                .sum::<u64>(),
+            "Total size should match the written files!"
+        );
+        assert!(output_fs.is_file(&PathBuf::from("404.html")));
+    }
+}
@@ -0,0 +1,220 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, RenderErrorReason,
+    ScopedJson,
+};
+use log::{debug, info, warn};
+use rhai::{
+    module_resolvers::DummyModuleResolver,
+    serde::{from_dynamic, to_dynamic},
+    Dynamic, Engine, Scope, AST,
+};
+use serde_json::Value;
+
+use crate::vfs::SourceFs;
+
+/// One template helper written as a Rhai script, the helper params are given to the script as
+/// the array `params`, and the hash as the map `hash`. The value of the last expression of the
+/// script is the value of the helper.
+#[derive(Clone)]
+struct ScriptHelper {
+    engine: Arc<Engine>,
+    script: AST,
+}
+
+impl HelperDef for ScriptHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        helper: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let script_error = |err: Box<rhai::EvalAltResult>| {
+            RenderErrorReason::Other(format!("Script helper '{}' failed! {err}", helper.name()))
+        };
+
+        let params = to_dynamic(
+            helper
+                .params()
+                .iter()
+                .map(|param| param.value())
+                .collect::<Vec<&Value>>(),
+        )
+        .map_err(script_error)?;
+        let hash = to_dynamic(
+            helper
+                .hash()
+                .iter()
+                .map(|(key, value)| (*key, value.value()))
+                .collect::<HashMap<&str, &Value>>(),
+        )
+        .map_err(script_error)?;
+
+        let mut scope = Scope::new();
+        scope.push_dynamic("params", params);
+        scope.push_dynamic("hash", hash);
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.script)
+            .map_err(script_error)?;
+
+        Ok(ScopedJson::Derived(
+            from_dynamic(&result).map_err(script_error)?,
+        ))
+    }
+}
+
+/// Template helpers written as Rhai scripts on the `_helpers/` directory, named by their file
+/// stem, e.g. `_helpers/reading_time.rhai` is used as `{{reading_time content}}`.
+///
+/// Scripts run sandboxed, Rhai has no filesystem or network access by itself, `import` of other
+/// scripts is disabled, and every call is limited on its operations and memory so a broken
+/// script fails the build instead of hanging it.
+#[derive(Clone)]
+pub struct ScriptHelpers {
+    engine: Arc<Engine>,
+    helpers: Vec<(String, ScriptHelper)>,
+}
+
+impl std::fmt::Debug for ScriptHelpers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.helpers.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+impl Default for ScriptHelpers {
+    fn default() -> Self {
+        Self {
+            engine: Arc::new(Self::sandboxed_engine()),
+            helpers: vec![],
+        }
+    }
+}
+
+impl ScriptHelpers {
+    pub const DIR_NAME: &'static str = "_helpers";
+
+    fn sandboxed_engine() -> Engine {
+        let mut engine = Engine::new();
+
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.set_max_operations(1_000_000);
+        engine.set_max_call_levels(64);
+        engine.set_max_expr_depths(64, 64);
+        engine.set_max_string_size(1 << 20);
+        engine.set_max_array_size(100_000);
+        engine.set_max_map_size(100_000);
+        engine.on_print(|text| info!(stage = "render"; "[ScriptHelper] {text}"));
+        engine.on_debug(|text, source, position| {
+            debug!(stage = "render"; "[ScriptHelper] {} {position}: {text}", source.unwrap_or(""))
+        });
+
+        engine
+    }
+
+    /// This function assume that `root_path` is the root of all of the pages, no `_helpers/`
+    /// directory means no script helpers.
+    pub fn from_dir<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut script_helpers = Self::default();
+        let helpers_dir = root_path.as_ref().join(Self::DIR_NAME);
+
+        let Ok(entries) = source_fs.read_dir(&helpers_dir) else {
+            return Ok(script_helpers);
+        };
+
+        for entry in entries {
+            let entry_path = entry.path;
+
+            if entry_path
+                .extension()
+                .is_some_and(|extension| extension == "rhai")
+            {
+                let Some(name) = entry_path.file_stem().and_then(|stem| stem.to_str()) else {
+                    warn!(stage = "load", file:% = entry_path.display(); "[ScriptHelpers::from_dir] Cannot get helper name of {entry_path:?}!");
+
+                    continue;
+                };
+
+                debug!(stage = "load", file:% = entry_path.display(); "[ScriptHelpers::from_dir] Registering script helper '{name}'");
+                script_helpers
+                    .register_script_helper(name, &source_fs.read_to_string(&entry_path)?)
+                    .map_err(|err| format!("Cannot compile {entry_path:?}! {err}"))?;
+            }
+        }
+
+        Ok(script_helpers)
+    }
+
+    pub fn register_script_helper(
+        &mut self,
+        name: &str,
+        script: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let script = self.engine.compile(script)?;
+
+        self.helpers.push((
+            name.to_owned(),
+            ScriptHelper {
+                engine: Arc::clone(&self.engine),
+                script,
+            },
+        ));
+
+        Ok(())
+    }
+
+    /// Register every script helper on `registry`, replacing any helper with the same name.
+    pub fn register_all(&self, registry: &mut Handlebars) {
+        for (name, helper) in &self.helpers {
+            registry.register_helper(name, Box::new(helper.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod script_helper_test {
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use crate::script_helper::ScriptHelpers;
+
+    #[test]
+    fn script_helper_rendered() {
+        let mut script_helpers = ScriptHelpers::default();
+        script_helpers
+            .register_script_helper("shout", r#"params[0].to_upper() + hash.end"#)
+            .unwrap();
+        script_helpers
+            .register_script_helper("forever", "loop {}")
+            .unwrap();
+        script_helpers
+            .register_script_helper("include", r#"import "secret" as secret; 1"#)
+            .unwrap();
+
+        let mut registry = Handlebars::new();
+        script_helpers.register_all(&mut registry);
+
+        assert_eq!(
+            registry
+                .render_template(r#"{{shout name end="!"}}"#, &json!({ "name": "hi" }))
+                .unwrap(),
+            "HI!"
+        );
+        assert!(
+            registry.render_template("{{forever}}", &json!({})).is_err(),
+            "Endless script should be stopped!"
+        );
+        assert!(
+            registry.render_template("{{include}}", &json!({})).is_err(),
+            "Script should not import files!"
+        );
+    }
+}
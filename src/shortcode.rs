@@ -0,0 +1,404 @@
+use std::{ops::Range, path::Path};
+
+use handlebars::Handlebars;
+use log::{debug, warn};
+use serde_json::{Map, Number, Value};
+
+use crate::vfs::SourceFs;
+
+const SHORTCODE_OPEN: &str = "{{<";
+const SHORTCODE_CLOSE: &str = ">}}";
+
+/// Reusable snippets that can be called from a markdown content, each shortcode is a Handlebars
+/// template on the `_shortcodes/` directory, named by its file stem. A shortcode is written as
+/// `{{< figure src="x.png" caption="A figure" >}}`, or with an inner body as
+/// `{{< callout kind="warning" >}}Be careful!{{< /callout >}}`, the body is given to the
+/// template as `body`.
+#[derive(Debug, Default, Clone)]
+pub struct Shortcodes {
+    registry: Handlebars<'static>,
+}
+
+/// One shortcode tag found on the content, the position are the byte range of the tag itself.
+struct ShortcodeTag<'a> {
+    start: usize,
+    end: usize,
+    name: &'a str,
+    args: &'a str,
+    is_closing: bool,
+}
+
+impl<'a> ShortcodeTag<'a> {
+    /// Find the first tag from `from`, skipping the tags inside `code_spans`.
+    fn find_next(content: &'a str, mut from: usize, code_spans: &[Range<usize>]) -> Option<Self> {
+        let mut start = from + content[from..].find(SHORTCODE_OPEN)?;
+        while let Some(code_span) = code_spans.iter().find(|span| span.contains(&start)) {
+            from = code_span.end;
+            start = from + content[from..].find(SHORTCODE_OPEN)?;
+        }
+        let inner_start = start + SHORTCODE_OPEN.len();
+        let inner_end = inner_start + content[inner_start..].find(SHORTCODE_CLOSE)?;
+
+        let inner = content[inner_start..inner_end].trim();
+        let (is_closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner.trim_start()),
+            None => (false, inner),
+        };
+        let (name, args) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+
+        Some(Self {
+            start,
+            end: inner_end + SHORTCODE_CLOSE.len(),
+            name,
+            args,
+            is_closing,
+        })
+    }
+}
+
+/// Byte ranges of the inline code spans on `content`, each is a run of backticks closed by the
+/// next run of the same length on the same paragraph.
+fn find_code_spans(content: &str) -> Vec<Range<usize>> {
+    let bytes = content.as_bytes();
+    let backtick_run_end = |mut index: usize| {
+        while bytes.get(index) == Some(&b'`') {
+            index += 1;
+        }
+        index
+    };
+
+    let mut code_spans = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] != b'`' {
+            index += 1;
+            continue;
+        }
+
+        let span_start = index;
+        index = backtick_run_end(index);
+        let run_length = index - span_start;
+        let paragraph_end = content[index..]
+            .find("\n\n")
+            .map_or(content.len(), |end| index + end);
+
+        // Without a closing run the backticks are just text
+        let mut search = index;
+        while search < paragraph_end {
+            if bytes[search] != b'`' {
+                search += 1;
+                continue;
+            }
+
+            let run_end = backtick_run_end(search);
+            if run_end - search == run_length {
+                code_spans.push(span_start..run_end);
+                index = run_end;
+                break;
+            }
+            search = run_end;
+        }
+    }
+
+    code_spans
+}
+
+impl Shortcodes {
+    pub const DIR_NAME: &'static str = "_shortcodes";
+
+    /// This function assume that `root_path` is the root of all of the pages, no `_shortcodes/`
+    /// directory means no shortcodes.
+    pub fn from_dir<P: AsRef<Path>>(
+        source_fs: &dyn SourceFs,
+        root_path: &P,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut shortcodes = Self::default();
+        let shortcodes_dir = root_path.as_ref().join(Self::DIR_NAME);
+
+        let Ok(entries) = source_fs.read_dir(&shortcodes_dir) else {
+            return Ok(shortcodes);
+        };
+
+        for entry in entries {
+            let entry_path = entry.path;
+
+            if entry_path
+                .extension()
+                .is_some_and(|extension| extension == "hbs")
+            {
+                let Some(name) = entry_path.file_stem().and_then(|stem| stem.to_str()) else {
+                    warn!(stage = "load", file:% = entry_path.display(); "[Shortcodes::from_dir] Cannot get shortcode name of {entry_path:?}!");
+
+                    continue;
+                };
+
+                debug!(stage = "load", file:% = entry_path.display(); "[Shortcodes::from_dir] Registering shortcode '{name}'");
+                shortcodes
+                    .register_shortcode(name, &source_fs.read_to_string(&entry_path)?)
+                    .map_err(|err| format!("Cannot compile {entry_path:?}! {err}"))?;
+            }
+        }
+
+        Ok(shortcodes)
+    }
+
+    pub fn register_shortcode(
+        &mut self,
+        name: &str,
+        template: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.registry.register_template_string(name, template)?;
+
+        Ok(())
+    }
+
+    /// Parse shortcode arguments, written as `key="value"` pairs separated by whitespace. Quoted
+    /// value are always string, unquoted `true`/`false` and numbers keep their type.
+    fn parse_args(raw_args: &str) -> Result<Map<String, Value>, String> {
+        let mut args = Map::new();
+        let mut remaining = raw_args.trim();
+
+        while !remaining.is_empty() {
+            let Some((key, rest)) = remaining.split_once('=') else {
+                return Err(format!("Shortcode argument '{remaining}' has no value!"));
+            };
+            let rest = rest.trim_start();
+
+            let (value, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+                let Some(quote_end) = quoted.find('"') else {
+                    return Err(format!("Shortcode argument '{key}' is not closed!"));
+                };
+
+                (
+                    Value::String(quoted[..quote_end].to_owned()),
+                    &quoted[quote_end + 1..],
+                )
+            } else {
+                let value_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let raw_value = &rest[..value_end];
+
+                let value = match raw_value {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => raw_value
+                        .parse::<i64>()
+                        .map(Number::from)
+                        .ok()
+                        .or_else(|| raw_value.parse::<f64>().ok().and_then(Number::from_f64))
+                        .map(Value::Number)
+                        .unwrap_or_else(|| Value::String(raw_value.to_owned())),
+                };
+
+                (value, &rest[value_end..])
+            };
+
+            args.insert(key.trim().to_owned(), value);
+            remaining = rest.trim_start();
+        }
+
+        Ok(args)
+    }
+
+    fn render_shortcode(
+        &self,
+        name: &str,
+        raw_args: &str,
+        body: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        if !self.registry.has_template(name) {
+            return Err(format!("Unknown shortcode '{name}'!").into());
+        }
+
+        let mut args = Self::parse_args(raw_args)?;
+
+        if let Some(body) = body {
+            args.insert("body".into(), Value::String(self.process(body)?));
+        }
+
+        Ok(self.registry.render(name, &args)?)
+    }
+
+    /// Replace every shortcodes outside of fenced code block and inline code span on `content`
+    /// with the render result of its template.
+    fn process_segment(&self, content: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut output = String::with_capacity(content.len());
+        let mut cursor = 0;
+        let code_spans = find_code_spans(content);
+
+        while let Some(tag) = ShortcodeTag::find_next(content, cursor, &code_spans) {
+            output.push_str(&content[cursor..tag.start]);
+
+            if tag.is_closing {
+                return Err(format!("Closing shortcode '{}' without opening!", tag.name).into());
+            }
+
+            // Look for the closing tag, every later opening tag of the same name has to be closed
+            // first, if there's none this is an inline shortcode
+            let mut depth = 1;
+            let mut closing_tag = None;
+            let mut search_from = tag.end;
+            while let Some(next_tag) = ShortcodeTag::find_next(content, search_from, &code_spans) {
+                search_from = next_tag.end;
+                if next_tag.name != tag.name {
+                    continue;
+                }
+
+                if next_tag.is_closing {
+                    depth -= 1;
+                } else {
+                    depth += 1;
+                }
+
+                if depth == 0 {
+                    closing_tag = Some(next_tag);
+                    break;
+                }
+            }
+
+            match closing_tag {
+                Some(closing_tag) => {
+                    let body = &content[tag.end..closing_tag.start];
+                    output.push_str(&self.render_shortcode(tag.name, tag.args, Some(body))?);
+                    cursor = closing_tag.end;
+                }
+                None => {
+                    output.push_str(&self.render_shortcode(tag.name, tag.args, None)?);
+                    cursor = tag.end;
+                }
+            }
+        }
+
+        output.push_str(&content[cursor..]);
+
+        Ok(output)
+    }
+
+    /// Render all of the shortcodes on the markdown `content`, shortcodes inside fenced code
+    /// blocks and inline code spans are kept as is.
+    pub fn process(&self, content: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if !content.contains(SHORTCODE_OPEN) {
+            return Ok(content.to_owned());
+        }
+
+        let mut output = String::with_capacity(content.len());
+        let mut segment = String::new();
+        let mut fence: Option<&str> = None;
+
+        for line in content.split_inclusive('\n') {
+            let trimmed_line = line.trim_start();
+
+            match fence {
+                Some(marker) => {
+                    output.push_str(line);
+
+                    if trimmed_line.starts_with(marker) {
+                        fence = None;
+                    }
+                }
+                None => {
+                    if let Some(marker) = ["```", "~~~"]
+                        .into_iter()
+                        .find(|marker| trimmed_line.starts_with(marker))
+                    {
+                        output.push_str(&self.process_segment(&segment)?);
+                        segment.clear();
+                        output.push_str(line);
+                        fence = Some(marker);
+                    } else {
+                        segment.push_str(line);
+                    }
+                }
+            }
+        }
+
+        output.push_str(&self.process_segment(&segment)?);
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod shortcode_test {
+    use crate::shortcode::Shortcodes;
+
+    fn get_test_shortcodes() -> Shortcodes {
+        let mut shortcodes = Shortcodes::default();
+        shortcodes
+            .register_shortcode(
+                "figure",
+                r#"<figure><img src="{{src}}"><figcaption>{{caption}}</figcaption></figure>"#,
+            )
+            .unwrap();
+        shortcodes
+            .register_shortcode("callout", r#"<div class="{{kind}}">{{{body}}}</div>"#)
+            .unwrap();
+
+        shortcodes
+    }
+
+    #[test]
+    fn inline_and_body_shortcodes() {
+        let content = "Look!\n\n{{< figure src=\"x.png\" caption=\"An X\" >}}\n\n{{< callout kind=\"warn\" >}}Be **careful**{{< /callout >}}";
+
+        let output = get_test_shortcodes().process(content).unwrap();
+
+        assert!(
+            output.contains(r#"<figure><img src="x.png"><figcaption>An X</figcaption></figure>"#),
+            "Inline shortcode should be rendered!"
+        );
+        assert!(
+            output.contains(r#"<div class="warn">Be **careful**</div>"#),
+            "Body shortcode should be rendered with the body!"
+        );
+    }
+
+    #[test]
+    fn code_block_shortcodes_ignored() {
+        let content = "```text\n{{< figure src=\"x.png\" >}}\n```\n";
+
+        let output = get_test_shortcodes().process(content).unwrap();
+
+        assert_eq!(
+            output, content,
+            "Shortcode inside code block should not be rendered!"
+        );
+    }
+
+    #[test]
+    fn unknown_shortcode_errors() {
+        let output = get_test_shortcodes().process("{{< youtube id=\"abc\" >}}");
+
+        assert!(output.is_err(), "Unknown shortcode should be an error!");
+    }
+
+    #[test]
+    fn code_span_shortcodes_ignored() {
+        let content = "Write `{{< figure src=\"x.png\" >}}` or ``{{< /callout >}}``, like {{< figure src=\"y.png\" >}}";
+
+        let output = get_test_shortcodes().process(content).unwrap();
+
+        assert!(
+            output
+                .starts_with("Write `{{< figure src=\"x.png\" >}}` or ``{{< /callout >}}``, like "),
+            "Shortcode inside code span should not be rendered!"
+        );
+        assert!(
+            output.contains(r#"<img src="y.png">"#),
+            "Shortcode after code span should be rendered!"
+        );
+    }
+
+    #[test]
+    fn shortcodes_paired_by_nesting() {
+        let content =
+            "{{< callout kind=\"a\" >}}\n\n{{< callout kind=\"b\" >}}Inner{{< /callout >}}";
+
+        let output = get_test_shortcodes().process(content).unwrap();
+
+        assert_eq!(
+            output, "<div class=\"a\"></div>\n\n<div class=\"b\">Inner</div>",
+            "Inline shortcode should not be paired with the closing tag of a later one!"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::SiteConfig,
+    pages::{write_outputs, Page},
+    plugin::{Plugin, Plugins},
+    renderer::Renderer,
+    report::BuildReport,
+    vfs::{DiskFs, DiskSink, OutputSink, SourceFs},
+};
+
+/// A whole site, the page tree and everything needed to render it, for using simplistis as a
+/// library. Nothing is printed to stdout, diagnostics are sent to the `log` facade.
+///
+/// ```no_run
+/// use simplistis::site::Site;
+///
+/// let site = Site::from_dir(&"website").unwrap();
+/// let outputs = site.render_to_memory().unwrap();
+///
+/// for (output_file, html) in outputs {
+///     println!("{output_file:?} is {} bytes", html.len());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Site {
+    root_page: Page,
+    renderer: Renderer,
+    /// Time spent on [`SiteBuilder::build`], for the build report
+    load_duration: Duration,
+}
+
+impl Site {
+    pub fn builder() -> SiteBuilder {
+        SiteBuilder::default()
+    }
+
+    /// Load the site on the template directory `root_path`, the same as the CLI.
+    pub fn from_dir<P: AsRef<Path>>(root_path: &P) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::builder().source_dir(root_path).build()
+    }
+
+    pub fn config(&self) -> &SiteConfig {
+        &self.renderer.config
+    }
+
+    /// The config is only used for rendering from here, the page paths (e.g. permalinks and
+    /// `ugly_urls`) are already set when the site is built.
+    pub fn config_mut(&mut self) -> &mut SiteConfig {
+        &mut self.renderer.config
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    pub fn renderer_mut(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+
+    pub fn root_page(&self) -> &Page {
+        &self.root_page
+    }
+
+    pub fn root_page_mut(&mut self) -> &mut Page {
+        &mut self.root_page
+    }
+
+    /// Render every output of the site, keyed by the output file relative to the output root.
+    pub fn render_to_memory(
+        &self,
+    ) -> Result<BTreeMap<PathBuf, Vec<u8>>, Box<dyn std::error::Error>> {
+        self.root_page.clone().render_to_memory(&self.renderer)
+    }
+
+    /// Render every output of the site to `output_dir`, it's created if it doesn't exist yet.
+    pub fn render_to_dir<P: AsRef<Path>>(
+        &self,
+        output_dir: &P,
+    ) -> Result<BuildReport, Box<dyn std::error::Error>> {
+        fs::create_dir_all(output_dir)?;
+
+        self.render_to_sink(&mut DiskSink::new(output_dir))
+    }
+
+    /// Render every output of the site to `output_sink`, e.g. a [`crate::vfs::TarSink`], and
+    /// returns the report of the whole build.
+    pub fn render_to_sink(
+        &self,
+        output_sink: &mut dyn OutputSink,
+    ) -> Result<BuildReport, Box<dyn std::error::Error>> {
+        let mut report = BuildReport::from_page(&self.root_page);
+        report.add_stage("load", self.load_duration);
+
+        let render_start = Instant::now();
+        let outputs = self.render_to_memory()?;
+        report.add_stage("render", render_start.elapsed());
+
+        let write_start = Instant::now();
+        write_outputs(&outputs, output_sink)?;
+        report.add_stage("write", write_start.elapsed());
+
+        report.add_outputs(&outputs);
+
+        Ok(report)
+    }
+}
+
+/// Builder of a [`Site`], either from a template directory, from memory, or both, e.g. a
+/// template directory with the config replaced.
+#[derive(Debug, Default)]
+pub struct SiteBuilder {
+    source_fs: Option<Box<dyn SourceFs>>,
+    source_dir: Option<PathBuf>,
+    config: Option<SiteConfig>,
+    root_page: Option<Page>,
+    shortcodes: Vec<(String, String)>,
+    script_helpers: Vec<(String, String)>,
+    plugins: Plugins,
+}
+
+impl SiteBuilder {
+    /// Load the config, shortcodes, helpers, data and pages from the template directory.
+    pub fn source_dir<P: AsRef<Path>>(mut self, root_path: &P) -> Self {
+        self.source_dir = Some(root_path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Read the template directory from `source_fs` instead of the local filesystem, e.g. a
+    /// [`crate::vfs::MemoryFs`] loaded from a tarball. Without [`SiteBuilder::source_dir`] the
+    /// template directory is the root of `source_fs`.
+    pub fn source_fs<F: SourceFs + 'static>(mut self, source_fs: F) -> Self {
+        self.source_fs = Some(Box::new(source_fs));
+        self
+    }
+
+    /// Replace the config, also when it's loaded from the template directory.
+    pub fn config(mut self, config: SiteConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Use a page tree built in memory instead of the pages on the template directory, see
+    /// [`Page::new_dir`].
+    pub fn root_page(mut self, root_page: Page) -> Self {
+        self.root_page = Some(root_page);
+        self
+    }
+
+    pub fn shortcode(mut self, name: &str, template: &str) -> Self {
+        self.shortcodes.push((name.to_owned(), template.to_owned()));
+        self
+    }
+
+    /// Add a Rhai script helper, see [`crate::script_helper::ScriptHelpers`].
+    pub fn script_helper(mut self, name: &str, script: &str) -> Self {
+        self.script_helpers
+            .push((name.to_owned(), script.to_owned()));
+        self
+    }
+
+    /// Add a plugin to the build, see [`Plugin`].
+    pub fn plugin<T: Plugin + 'static>(mut self, plugin: T) -> Self {
+        self.plugins.register(Arc::new(plugin));
+        self
+    }
+
+    pub fn build(self) -> Result<Site, Box<dyn std::error::Error>> {
+        let load_start = Instant::now();
+        let source_dir = match (&self.source_dir, &self.source_fs) {
+            (Some(source_dir), _) => Some(source_dir.clone()),
+            (None, Some(_)) => Some(PathBuf::new()),
+            (None, None) => None,
+        };
+        let source_fs = self.source_fs.unwrap_or_else(|| Box::new(DiskFs));
+
+        let mut renderer = match &source_dir {
+            Some(source_dir) => Renderer::from_dir(source_fs.as_ref(), source_dir)?,
+            None => Renderer::default(),
+        };
+
+        if let Some(config) = self.config {
+            config.validate()?;
+            renderer.config = config;
+        }
+
+        for (name, template) in &self.shortcodes {
+            renderer.shortcodes.register_shortcode(name, template)?;
+        }
+
+        for (name, script) in &self.script_helpers {
+            renderer
+                .script_helpers
+                .register_script_helper(name, script)?;
+        }
+
+        renderer.plugins = self.plugins;
+
+        let mut root_page = match (self.root_page, &source_dir) {
+            (Some(root_page), _) => root_page,
+            (None, Some(source_dir)) => Page::from_dir(
+                source_fs.as_ref(),
+                source_dir,
+                &renderer.config,
+                &renderer.data,
+            )
+            .ok_or_else(|| format!("Cannot load the pages of {source_dir:?}!"))?,
+            (None, None) => return Err("Site needs a source directory or a root page!".into()),
+        };
+
+        renderer.plugins.page_tree(&mut root_page)?;
+
+        Ok(Site {
+            root_page,
+            renderer,
+            load_duration: load_start.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod site_test {
+    use std::path::{Path, PathBuf};
+
+    use crate::{
+        config::SiteConfig,
+        content::Content,
+        pages::Page,
+        site::Site,
+        vfs::{MemoryFs, SourceFs},
+    };
+
+    #[test]
+    fn site_rendered_from_memory() {
+        let config = SiteConfig::default();
+        let mut root_page = Page::new_dir(
+            "",
+            Content::from_markdown(
+                "---\ntitle = \"Home\"\n---\n\nHi!".into(),
+                "_index.md".into(),
+            ),
+            "<h1>{{page.title}}</h1>{{#each site.pages}}<a href=\"{{url}}\">{{title}}</a>{{/each}}"
+                .into(),
+            &config,
+        );
+        root_page.add_content(
+            Content::from_markdown("{{< greet name=\"you\" >}}".into(), "Hello There.md".into()),
+            "{{{content}}}",
+            &config,
+        );
+
+        let site = Site::builder()
+            .config(config)
+            .root_page(root_page)
+            .shortcode("greet", "Hello {{name}}!")
+            .build()
+            .unwrap();
+
+        assert_eq!(site.root_page().child()[0].path(), "hello-there");
+
+        let outputs = site.render_to_memory().unwrap();
+
+        assert_eq!(
+            outputs.keys().collect::<Vec<_>>(),
+            vec![
+                &PathBuf::from("hello-there/index.html"),
+                &PathBuf::from("index.html")
+            ]
+        );
+        assert_eq!(
+            outputs[&PathBuf::from("index.html")],
+            br#"<h1>Home</h1><a href="/hello-there/">Hello There</a>"#
+        );
+        assert_eq!(
+            outputs[&PathBuf::from("hello-there/index.html")],
+            b"<p>Hello you!</p>\n"
+        );
+    }
+
+    #[test]
+    fn site_rendered_from_memory_fs() {
+        let source_fs = MemoryFs::new()
+            .with_file(&"config.toml", "ugly_urls = true")
+            .with_file(&"_index.md", "Home, see [the post](blog/post.md).")
+            .with_file(&"template.hbs", "{{{content}}}")
+            .with_file(&"_shortcodes/greet.hbs", "Hello {{name}}!")
+            .with_file(&"blog/_index.md", "Blog")
+            .with_file(&"blog/template.hbs", "{{{content}}}")
+            .with_file(&"blog/content.hbs", "{{{content}}}")
+            .with_file(&"blog/post.md", "{{< greet name=\"you\" >}}");
+
+        let site = Site::builder().source_fs(source_fs).build().unwrap();
+        let mut output_fs = MemoryFs::new();
+        site.render_to_sink(&mut output_fs).unwrap();
+
+        assert_eq!(
+            output_fs.read_to_string(Path::new("index.html")).unwrap(),
+            "<p>Home, see <a href=\"/blog/post.html\">the post</a>.</p>\n"
+        );
+        assert_eq!(
+            output_fs
+                .read_to_string(Path::new("blog/post.html"))
+                .unwrap(),
+            "<p>Hello you!</p>\n",
+            "Every source should be read from the source filesystem!"
+        );
+    }
+
+    #[test]
+    fn invalid_config_rejected() {
+        let source_fs = MemoryFs::new()
+            .with_file(&"_index.md", "Home")
+            .with_file(&"template.hbs", "{{{content}}}");
+        let config = SiteConfig {
+            sitemap: true,
+            ..Default::default()
+        };
+
+        assert!(
+            Site::builder()
+                .source_fs(source_fs)
+                .config(config)
+                .build()
+                .is_err(),
+            "Config passed in code should be validated like config.toml!"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fmt::Write, path::PathBuf};
+
+use log::debug;
+
+use crate::{config::SiteConfig, i18n::Translations, pages::Page, permalink::join_page_path};
+
+pub const FILE_NAME: &str = "sitemap.xml";
+
+/// The sitemap of `lang`, relative to the output root e.g. `id/sitemap.xml`.
+pub fn sitemap_file(config: &SiteConfig, lang: &str) -> PathBuf {
+    PathBuf::from(join_page_path([
+        config.language_prefix(lang).as_str(),
+        FILE_NAME,
+    ]))
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Render one sitemap of the pages on the tree of `language_root`.
+fn render_sitemap(
+    language_root: &Page,
+    translations: &Translations,
+    config: &SiteConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let base_url = config.base_url.trim_end_matches('/');
+    let mut sitemap = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:xhtml="http://www.w3.org/1999/xhtml">
+"#,
+    );
+
+    let mut pages = vec![language_root];
+    while let Some(page) = pages.pop() {
+        pages.extend(page.child.iter().rev());
+
+        let metadata = &page.content.metadata;
+        let page_url = page.url();
+        writeln!(sitemap, "  <url>")?;
+        writeln!(
+            sitemap,
+            "    <loc>{}</loc>",
+            escape_xml(&format!("{base_url}{page_url}"))
+        )?;
+        // Unset `updated_at` is the build time, which would mark the page as changed every build
+        if metadata.is_date_set {
+            writeln!(
+                sitemap,
+                "    <lastmod>{}</lastmod>",
+                metadata.updated_at.format("%Y-%m-%d")
+            )?;
+        }
+
+        let page_translations = translations.for_page(page, config);
+        // The alternates of a page should include the page itself
+        if let (false, Some(lang)) = (page_translations.is_empty(), &metadata.lang) {
+            let alternates = std::iter::once((lang.as_str(), page_url.as_str())).chain(
+                page_translations
+                    .iter()
+                    .map(|translation| (translation.lang.as_str(), translation.url.as_str())),
+            );
+
+            for (lang, url) in alternates {
+                writeln!(
+                    sitemap,
+                    r#"    <xhtml:link rel="alternate" hreflang="{}" href="{}"/>"#,
+                    escape_xml(lang),
+                    escape_xml(&format!("{base_url}{url}"))
+                )?;
+            }
+        }
+
+        writeln!(sitemap, "  </url>")?;
+    }
+
+    sitemap.push_str("</urlset>\n");
+
+    Ok(sitemap)
+}
+
+/// Render the sitemap of every language, each lists the pages of its language with every
+/// translation of the page as `xhtml:link` with its `hreflang`. The special pages are not
+/// listed. This function assume that `root_page` is the root of all of the pages.
+pub fn render_sitemaps(
+    root_page: &Page,
+    translations: &Translations,
+    config: &SiteConfig,
+) -> Result<Vec<(PathBuf, String)>, Box<dyn std::error::Error>> {
+    let mut outputs = Vec::new();
+
+    for language_root in std::iter::once(root_page).chain(root_page.language_roots.iter()) {
+        let lang = language_root
+            .content
+            .metadata
+            .lang
+            .as_deref()
+            .unwrap_or(config.default_language());
+        let output_file = sitemap_file(config, lang);
+
+        debug!(stage = "render", file:% = output_file.display(); "[sitemap::render_sitemaps] Rendering sitemap of '{lang}'");
+        outputs.push((
+            output_file,
+            render_sitemap(language_root, translations, config)?,
+        ));
+    }
+
+    Ok(outputs)
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+/// What to do with non-ASCII characters when making a slug.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugUnicode {
+    /// Replace them with their closest ASCII characters, e.g. `Ünïcödé` to `unicode`
+    #[default]
+    Transliterate,
+    /// Keep the unicode letters and numbers as is
+    Keep,
+    /// Remove them
+    Remove,
+}
+
+/// Rules to make a slug, set on `config.toml` as `[slugify]`.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct SlugifyConfig {
+    pub lowercase: bool,
+    pub unicode: SlugUnicode,
+    /// Replaces every character that is not a letter or number
+    pub separator: String,
+    /// By default only the slug made from the file name is slugified, this also slugify the
+    /// `slug` set on the front matter
+    pub normalize_user_slugs: bool,
+}
+
+impl Default for SlugifyConfig {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            unicode: SlugUnicode::default(),
+            separator: "-".into(),
+            normalize_user_slugs: false,
+        }
+    }
+}
+
+impl SlugifyConfig {
+    /// Turn `text` into a URL friendly string following the rules, every character that is not
+    /// a letter or number will be replaced with the separator, and no separator would be
+    /// repeated or be on either end.
+    pub fn slugify(&self, text: &str) -> String {
+        let text = match self.unicode {
+            SlugUnicode::Transliterate => deunicode::deunicode(text),
+            SlugUnicode::Keep | SlugUnicode::Remove => text.to_owned(),
+        };
+
+        let mut slug = String::with_capacity(text.len());
+        let mut is_separator_pending = false;
+
+        for character in text.chars() {
+            let is_kept = character.is_ascii_alphanumeric()
+                || (self.unicode == SlugUnicode::Keep && character.is_alphanumeric());
+
+            if is_kept {
+                // Separator is only pushed before a kept character, so it's never on either end
+                if is_separator_pending && !slug.is_empty() {
+                    slug.push_str(&self.separator);
+                }
+                is_separator_pending = false;
+
+                if self.lowercase {
+                    slug.extend(character.to_lowercase());
+                } else {
+                    slug.push(character);
+                }
+            } else if self.unicode == SlugUnicode::Remove && !character.is_ascii() {
+                continue;
+            } else {
+                is_separator_pending = true;
+            }
+        }
+
+        slug
+    }
+}
+
+/// Slugify `text` with the default rules, see [`SlugifyConfig::slugify`].
+pub fn slugify(text: &str) -> String {
+    SlugifyConfig::default().slugify(text)
+}
+
+#[cfg(test)]
+mod slugify_test {
+    use crate::slugify::{slugify, SlugUnicode, SlugifyConfig};
+
+    #[test]
+    fn punctuation_and_spaces_replaced() {
+        assert_eq!(slugify("Hello World!"), "hello-world");
+        assert_eq!(slugify("  What is it?  "), "what-is-it");
+        assert_eq!(slugify("a -- b"), "a-b");
+    }
+
+    #[test]
+    fn unicode_rules() {
+        let mut config = SlugifyConfig::default();
+
+        assert_eq!(config.slugify("Ünïcödé Post"), "unicode-post");
+
+        config.unicode = SlugUnicode::Keep;
+        assert_eq!(config.slugify("Ünïcödé Post"), "ünïcödé-post");
+
+        config.unicode = SlugUnicode::Remove;
+        assert_eq!(config.slugify("Ünïcödé Post"), "ncd-post");
+
+        config.lowercase = false;
+        config.separator = "_".into();
+        assert_eq!(config.slugify("Hello World!"), "Hello_World");
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use crate::{highlight::escape_attribute, slugify::SlugifyConfig};
+
+/// Where the `#` anchor link of a heading would be placed, if any.
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorLinks {
+    #[default]
+    None,
+    Before,
+    After,
+}
+
+/// One heading of a content, `children` are the headings with deeper level that comes after it.
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct TocEntry {
+    pub level: u32,
+    pub text: String,
+    pub id: String,
+    pub children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    /// Nest a flat list of headings, in document order, into a tree.
+    fn into_tree(flat_entries: Vec<TocEntry>) -> Vec<TocEntry> {
+        let mut roots = Vec::<TocEntry>::new();
+
+        for entry in flat_entries {
+            let mut siblings = &mut roots;
+
+            // Walk down the last entry as long as it is a shallower heading than the current one
+            while siblings
+                .last()
+                .is_some_and(|last_entry| last_entry.level < entry.level)
+            {
+                siblings = &mut siblings.last_mut().unwrap().children;
+            }
+
+            siblings.push(entry);
+        }
+
+        roots
+    }
+}
+
+/// The id is escaped, as the one set by heading attributes is copied verbatim.
+fn anchor_link_html(id: &str) -> String {
+    format!(
+        "<a class=\"anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>",
+        escape_attribute(id)
+    )
+}
+
+/// Walk the markdown event stream, giving every heading a unique `id` (keeping the one set by
+/// heading attributes), and optionally an anchor link. The ids are made from the heading text
+/// with the site slugify rules. Returns the events with the table of contents of the headings.
+pub fn add_heading_ids<'a, I: Iterator<Item = Event<'a>>>(
+    events: I,
+    anchor_links: AnchorLinks,
+    slugify_config: &SlugifyConfig,
+) -> (Vec<Event<'a>>, Vec<TocEntry>) {
+    let mut output = Vec::new();
+    let mut flat_toc = Vec::new();
+    let mut used_ids = HashMap::<String, usize>::new();
+    let mut current_heading: Option<(Tag<'a>, String, Vec<Event<'a>>)> = None;
+
+    for event in events {
+        match (&mut current_heading, event) {
+            (None, Event::Start(tag @ Tag::Heading { .. })) => {
+                current_heading = Some((tag, String::new(), vec![]));
+            }
+            (Some(_), Event::End(TagEnd::Heading(level))) => {
+                let (tag, text, inner_events) = current_heading.take().unwrap();
+                let Tag::Heading {
+                    id, classes, attrs, ..
+                } = tag
+                else {
+                    unreachable!("Only heading tag is saved as current heading");
+                };
+
+                let base_id = match &id {
+                    Some(id) => id.to_string(),
+                    None => match slugify_config.slugify(&text) {
+                        slug if slug.is_empty() => "section".into(),
+                        slug => slug,
+                    },
+                };
+
+                // Same headings will get the `-1`, `-2`, ... suffix in order
+                let mut unique_id = base_id.clone();
+                while let Some(count) = used_ids.get_mut(&unique_id) {
+                    *count += 1;
+                    unique_id = format!("{base_id}-{count}");
+                }
+                used_ids.insert(unique_id.clone(), 0);
+
+                output.push(Event::Start(Tag::Heading {
+                    level,
+                    id: Some(unique_id.clone().into()),
+                    classes,
+                    attrs,
+                }));
+                if anchor_links == AnchorLinks::Before {
+                    output.push(Event::Html(anchor_link_html(&unique_id).into()));
+                }
+                output.extend(inner_events);
+                if anchor_links == AnchorLinks::After {
+                    output.push(Event::Html(anchor_link_html(&unique_id).into()));
+                }
+                output.push(Event::End(TagEnd::Heading(level)));
+
+                flat_toc.push(TocEntry {
+                    level: level as u32,
+                    text: text.trim().to_owned(),
+                    id: unique_id,
+                    children: vec![],
+                });
+            }
+            (Some((_, text, inner_events)), event) => {
+                if let Event::Text(inner_text) | Event::Code(inner_text) = &event {
+                    text.push_str(inner_text);
+                }
+
+                inner_events.push(event);
+            }
+            (None, event) => output.push(event),
+        }
+    }
+
+    (output, TocEntry::into_tree(flat_toc))
+}
+
+#[cfg(test)]
+mod toc_test {
+    use pulldown_cmark::{html, Options, Parser};
+
+    use crate::{
+        slugify::{SlugUnicode, SlugifyConfig},
+        toc::{add_heading_ids, AnchorLinks},
+    };
+
+    #[test]
+    fn heading_ids_unique() {
+        let markdown = "# Intro\n\n## Usage\n\n### Usage\n\n## Usage\n\n# Custom {#my-id}";
+        let (events, toc) = add_heading_ids(
+            Parser::new_ext(markdown, Options::ENABLE_HEADING_ATTRIBUTES),
+            AnchorLinks::After,
+            &SlugifyConfig::default(),
+        );
+
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, events.into_iter());
+
+        assert!(html_output.contains("<h2 id=\"usage\">"));
+        assert!(html_output.contains("<h3 id=\"usage-1\">"));
+        assert!(html_output.contains("<h2 id=\"usage-2\">"));
+        assert!(
+            html_output.contains("<h1 id=\"my-id\">Custom<a class=\"anchor\" href=\"#my-id\""),
+            "Heading attribute id should be kept!"
+        );
+
+        assert_eq!(toc.len(), 2, "There should be two top level headings!");
+        assert_eq!(toc[0].children.len(), 2, "Intro should have two children!");
+        assert_eq!(
+            toc[0].children[0].children[0].id, "usage-1",
+            "Deeper heading should be nested!"
+        );
+    }
+
+    #[test]
+    fn anchor_link_id_escaped() {
+        let (events, _) = add_heading_ids(
+            Parser::new_ext("# Custom {#a\"b&c}", Options::ENABLE_HEADING_ATTRIBUTES),
+            AnchorLinks::Before,
+            &SlugifyConfig::default(),
+        );
+
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, events.into_iter());
+
+        assert!(
+            html_output.contains("href=\"#a&quot;b&amp;c\""),
+            "Anchor link id should be escaped! Got: {html_output}"
+        );
+    }
+
+    #[test]
+    fn non_ascii_heading_ids() {
+        let heading_id = |slugify_config: &SlugifyConfig| {
+            let (_, toc) = add_heading_ids(
+                Parser::new("## Ünïcödé Heading"),
+                AnchorLinks::None,
+                slugify_config,
+            );
+
+            toc[0].id.clone()
+        };
+
+        let mut slugify_config = SlugifyConfig::default();
+        assert_eq!(heading_id(&slugify_config), "unicode-heading");
+
+        slugify_config.unicode = SlugUnicode::Keep;
+        assert_eq!(
+            heading_id(&slugify_config),
+            "ünïcödé-heading",
+            "Heading id should follow the site slugify rules!"
+        );
+    }
+}
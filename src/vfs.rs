@@ -0,0 +1,450 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// One entry of a directory on a [`SourceFs`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct DirEntry {
+    /// The directory path joined with the entry name
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Where the sources of a site (config, pages, templates, data, etc.) are read from.
+pub trait SourceFs: Send + Sync + std::fmt::Debug {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Entries of the directory `path`, sorted by their path.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    fn is_file(&self, path: &Path) -> bool;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Where the outputs of a site are written to.
+pub trait OutputSink {
+    /// `path` is relative to the output root.
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+/// Archive path of `path`, always separated by `/`.
+fn archive_path(path: &Path) -> String {
+    normalize(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Remove the `.` and root components, so `./blog/_index.md` and `/blog/_index.md` are the same
+/// file on a [`MemoryFs`].
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_) | Component::ParentDir))
+        .collect()
+}
+
+/// True if `path` is relative and stays inside the directory it's joined to, so it has no `..`,
+/// root or prefix (e.g. `C:`) component.
+fn is_enclosed(path: &Path) -> bool {
+    path.components().all(|component| {
+        !matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    })
+}
+
+/// The local filesystem, paths are used as is.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskFs;
+
+impl SourceFs for DiskFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+
+            entries.push(DirEntry {
+                path: entry.path(),
+                is_dir: entry.file_type()?.is_dir(),
+            });
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(entries)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// Writes every output under `output_dir`, creating the directories as needed.
+#[derive(Debug, Clone)]
+pub struct DiskSink {
+    output_dir: PathBuf,
+}
+
+impl DiskSink {
+    pub fn new<P: AsRef<Path>>(output_dir: &P) -> Self {
+        Self {
+            output_dir: output_dir.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl OutputSink for DiskSink {
+    /// A path that would be written outside of `output_dir` is an error.
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if !is_enclosed(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsafe output path {path:?}!"),
+            ));
+        }
+
+        let output_path = self.output_dir.join(path);
+
+        if let Some(output_parent) = output_path.parent() {
+            fs::create_dir_all(output_parent)?;
+        }
+
+        fs::write(output_path, contents)
+    }
+}
+
+/// Files kept in memory, both a [`SourceFs`] and an [`OutputSink`]. Directories are implied by
+/// the file paths, so there's no empty directory.
+#[derive(Default, Clone)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl std::fmt::Debug for MemoryFs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.files.keys()).finish()
+    }
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the file on `path`.
+    pub fn insert<P: AsRef<Path>, C: Into<Vec<u8>>>(&mut self, path: &P, contents: C) {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+
+    /// Builder style of [`MemoryFs::insert`].
+    pub fn with_file<P: AsRef<Path>, C: Into<Vec<u8>>>(mut self, path: &P, contents: C) -> Self {
+        self.insert(path, contents);
+        self
+    }
+
+    pub fn files(&self) -> &BTreeMap<PathBuf, Vec<u8>> {
+        &self.files
+    }
+
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files
+    }
+
+    /// Load every file of a tar archive, anything other than a regular file is skipped, and a
+    /// file with an unsafe path (e.g. `../`) is an error.
+    pub fn from_tar<R: Read>(reader: R) -> io::Result<Self> {
+        let mut memory_fs = Self::default();
+        let mut archive = tar::Archive::new(reader);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path()?.into_owned();
+            if !is_enclosed(&path) {
+                return Err(io::Error::other(format!(
+                    "Unsafe path {path:?} on the tar archive!"
+                )));
+            }
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+
+            memory_fs.insert(&path, contents);
+        }
+
+        Ok(memory_fs)
+    }
+
+    /// Load every file of a zip archive, a file with an unsafe path (e.g. `../`) is an error.
+    pub fn from_zip<R: Read + Seek>(reader: R) -> io::Result<Self> {
+        let mut memory_fs = Self::default();
+        let mut archive = zip::ZipArchive::new(reader).map_err(io::Error::other)?;
+
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).map_err(io::Error::other)?;
+            if !file.is_file() {
+                continue;
+            }
+
+            let Some(path) = file.enclosed_name() else {
+                return Err(io::Error::other(format!(
+                    "Unsafe path '{}' on the zip archive!",
+                    file.name()
+                )));
+            };
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+
+            memory_fs.insert(&path, contents);
+        }
+
+        Ok(memory_fs)
+    }
+}
+
+impl SourceFs for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{path:?} not found")))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let dir = normalize(path);
+        let mut entries: BTreeMap<PathBuf, bool> = BTreeMap::new();
+
+        for file in self.files.keys() {
+            let Ok(relative_path) = file.strip_prefix(&dir) else {
+                continue;
+            };
+
+            let mut components = relative_path.components();
+            let Some(name) = components.next() else {
+                continue;
+            };
+
+            let is_dir = components.next().is_some();
+            *entries.entry(path.join(name)).or_default() |= is_dir;
+        }
+
+        if entries.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{path:?} is not a directory"),
+            ));
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(path, is_dir)| DirEntry { path, is_dir })
+            .collect())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        let dir = normalize(path);
+
+        self.files
+            .keys()
+            .any(|file| file != &dir && file.starts_with(&dir))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(&normalize(path))
+    }
+}
+
+impl OutputSink for MemoryFs {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.insert(&path, contents);
+
+        Ok(())
+    }
+}
+
+/// Writes every output to a tar archive, the archive is only complete after
+/// [`TarSink::finish`].
+pub struct TarSink<W: Write> {
+    builder: tar::Builder<W>,
+}
+
+impl<W: Write> TarSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            builder: tar::Builder::new(writer),
+        }
+    }
+
+    /// Write the end of the archive, and returns the writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.builder.into_inner()
+    }
+}
+
+impl<W: Write> OutputSink for TarSink<W> {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+
+        self.builder
+            .append_data(&mut header, archive_path(path), contents)
+    }
+}
+
+/// Writes every output to a zip archive, the archive is only complete after
+/// [`ZipSink::finish`].
+pub struct ZipSink<W: Write + Seek> {
+    writer: zip::ZipWriter<W>,
+}
+
+impl<W: Write + Seek> ZipSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: zip::ZipWriter::new(writer),
+        }
+    }
+
+    /// Write the central directory of the archive, and returns the writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.writer.finish().map_err(io::Error::other)
+    }
+}
+
+impl<W: Write + Seek> OutputSink for ZipSink<W> {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.writer
+            .start_file(archive_path(path), zip::write::SimpleFileOptions::default())
+            .map_err(io::Error::other)?;
+
+        self.writer.write_all(contents)
+    }
+}
+
+#[cfg(test)]
+mod vfs_test {
+    use std::{
+        io::Cursor,
+        path::{Path, PathBuf},
+    };
+
+    use crate::vfs::{DirEntry, DiskSink, MemoryFs, OutputSink, SourceFs, TarSink, ZipSink};
+
+    #[test]
+    fn memory_fs_read() {
+        let memory_fs = MemoryFs::new()
+            .with_file(&"./site/_index.md", "Hi!")
+            .with_file(&"site/blog/post.md", "Post");
+
+        assert_eq!(
+            memory_fs
+                .read_to_string(Path::new("site/_index.md"))
+                .unwrap(),
+            "Hi!"
+        );
+        assert!(memory_fs.is_dir(Path::new("site/blog")));
+        assert!(!memory_fs.is_file(Path::new("site/blog")));
+        assert_eq!(
+            memory_fs.read_dir(Path::new("site")).unwrap(),
+            vec![
+                DirEntry {
+                    path: PathBuf::from("site/_index.md"),
+                    is_dir: false
+                },
+                DirEntry {
+                    path: PathBuf::from("site/blog"),
+                    is_dir: true
+                }
+            ]
+        );
+        assert!(
+            memory_fs.read_dir(Path::new("missing")).is_err(),
+            "Missing directory should be an error!"
+        );
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let mut tar_sink = TarSink::new(Vec::new());
+        tar_sink
+            .write(Path::new("blog/index.html"), b"<p>Hi!</p>")
+            .unwrap();
+        let tar = tar_sink.finish().unwrap();
+
+        let mut zip_sink = ZipSink::new(Cursor::new(Vec::new()));
+        zip_sink
+            .write(Path::new("blog/index.html"), b"<p>Hi!</p>")
+            .unwrap();
+        let zip = zip_sink.finish().unwrap();
+
+        for memory_fs in [
+            MemoryFs::from_tar(tar.as_slice()).unwrap(),
+            MemoryFs::from_zip(zip).unwrap(),
+        ] {
+            assert_eq!(
+                memory_fs.read(Path::new("blog/index.html")).unwrap(),
+                b"<p>Hi!</p>",
+                "Archive should contain the written file!"
+            );
+        }
+    }
+
+    #[test]
+    fn unsafe_paths_rejected() {
+        let output_dir = std::env::temp_dir().join("simplistis-unsafe-paths");
+        let mut disk_sink = DiskSink::new(&output_dir);
+
+        for path in [
+            "../outside.html",
+            "/etc/outside.html",
+            "blog/../../outside.html",
+        ] {
+            assert!(
+                disk_sink.write(Path::new(path), b"Hi!").is_err(),
+                "Output path {path} should be rejected!"
+            );
+        }
+        assert!(!output_dir.with_file_name("outside.html").exists());
+
+        // `tar::Builder` refuses `..` on its own, so the name is set on the header directly
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..13].copy_from_slice(b"../outside.md");
+        header.set_size(3);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, "Hi!".as_bytes()).unwrap();
+        let tar = builder.into_inner().unwrap();
+
+        assert!(
+            MemoryFs::from_tar(tar.as_slice())
+                .is_err_and(|err| err.to_string().starts_with("Unsafe path")),
+            "Tar entry outside of the root should be rejected!"
+        );
+    }
+}